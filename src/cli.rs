@@ -1,6 +1,14 @@
+use std::fmt;
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
 
-use clap::{ArgAction, Parser, Subcommand, ValueEnum};
+use clap::builder::ArgPredicate;
+use clap::{ArgAction, ArgGroup, Args, Parser, Subcommand, ValueEnum};
+use clap_complete::ArgValueCandidates;
+
+use crate::complete;
+use crate::output::{self, Template};
 
 #[derive(Debug, Parser)]
 #[command(name = "pearls")]
@@ -19,6 +27,10 @@ pub struct Cli {
     #[arg(long, global = true)]
     json: bool,
 
+    /// Disable colored output (also honored via the NO_COLOR env var)
+    #[arg(long, global = true)]
+    no_color: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -41,11 +53,126 @@ impl Cli {
     pub fn json(&self) -> bool {
         self.json
     }
+
+    pub fn no_color(&self) -> bool {
+        self.no_color
+    }
 }
 
 #[derive(Debug, Subcommand)]
 pub enum Commands {
     Tasks(TasksCommand),
+    /// Export all tasks and dependencies to stdout
+    Export {
+        #[arg(
+            long,
+            value_name = "FORMAT",
+            default_value = "json",
+            help = "Output format"
+        )]
+        format: ExportFormat,
+    },
+    /// Import tasks and dependencies from a previous export
+    Import {
+        #[arg(
+            long,
+            value_name = "FORMAT",
+            default_value = "json",
+            help = "Input format"
+        )]
+        format: ImportFormat,
+        #[arg(
+            long,
+            value_name = "PATH",
+            required_unless_present = "path",
+            help = "File to import from"
+        )]
+        from: Option<PathBuf>,
+        #[arg(
+            value_name = "PATH",
+            conflicts_with = "from",
+            help = "File to import from (alternative to --from)"
+        )]
+        path: Option<PathBuf>,
+        #[arg(
+            long,
+            help = "Assign fresh ids instead of keeping the ids from the file (always on for markdown)"
+        )]
+        remap_ids: bool,
+        #[arg(
+            long,
+            help = "Update existing tasks matched by id instead of adding new ones (csv only)"
+        )]
+        update: bool,
+    },
+    /// Sync the task graph with a git-friendly JSON lines file
+    #[command(group(ArgGroup::new("direction").required(true).args(["export", "import"])))]
+    Sync {
+        #[arg(
+            long,
+            value_name = "PATH",
+            num_args = 0..=1,
+            help = "Write one task per line to PATH (defaults to tasks.jsonl next to the database)"
+        )]
+        export: Option<Option<PathBuf>>,
+        #[arg(
+            long,
+            value_name = "PATH",
+            num_args = 0..=1,
+            help = "Merge changes from PATH into the database (defaults to tasks.jsonl next to the database)"
+        )]
+        import: Option<Option<PathBuf>>,
+    },
+    /// Browse and triage tasks in an interactive terminal UI
+    Tui,
+    /// Print an event whenever a task is added, claimed, updated, closed, or unblocked
+    Watch {
+        #[arg(
+            long,
+            value_name = "DURATION",
+            default_value = "1s",
+            value_parser = parse_duration,
+            help = "How often to check the database for changes (e.g. 500ms, 2s)"
+        )]
+        interval: Duration,
+    },
+    /// Print a shell completion script (completes task ids from the database)
+    Completions {
+        #[arg(value_name = "SHELL")]
+        shell: CompletionShell,
+    },
+    /// Print the JSON Schema of each command's --json output
+    Schema {
+        #[arg(
+            value_name = "COMMAND",
+            help = "Only print the schema for COMMAND (e.g. tasks-show); prints all when omitted"
+        )]
+        command: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Json,
+    Markdown,
+    Csv,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub enum ImportFormat {
+    Json,
+    Markdown,
+    Beads,
+    Csv,
 }
 
 #[derive(Debug, Parser)]
@@ -62,7 +189,7 @@ pub enum TaskSubcommand {
             long,
             value_name = "STATES",
             value_delimiter = ',',
-            default_value = "ready,blocked,in_progress",
+            default_value = "ready,blocked,deferred,in_progress,needs_human",
             help = "Comma-separated states to include"
         )]
         state: Vec<TaskState>,
@@ -70,19 +197,136 @@ pub enum TaskSubcommand {
             long,
             value_name = "OFFSET",
             default_value_t = 0,
+            conflicts_with = "cursor",
             help = "Number of tasks to skip before listing"
         )]
         offset: u64,
+        #[arg(
+            long,
+            value_name = "CURSOR",
+            help = "Continue after the next_cursor returned by a previous page"
+        )]
+        cursor: Option<String>,
+        #[arg(
+            long,
+            help = "With --json, print a bare array of tasks instead of {items, total, next_cursor}"
+        )]
+        bare_array: bool,
+        #[arg(
+            long,
+            conflicts_with_all = ["bare_array", "count"],
+            help = "Stream one compact JSON object per line (no limit unless --limit is given)"
+        )]
+        json_lines: bool,
         #[arg(
             long,
             value_name = "LIMIT",
             default_value_t = 20,
+            // --json-lines streams everything (u64::MAX) unless a limit is given.
+            default_value_if("json_lines", ArgPredicate::Equals("true".into()), "18446744073709551615"),
             help = "Maximum number of tasks to list"
         )]
         limit: u64,
+        #[arg(
+            long,
+            value_name = "FIELD",
+            default_value = "id",
+            help = "Sort by priority, id, created, or updated (ties broken by id)"
+        )]
+        sort: TaskSort,
+        #[arg(long, help = "Sort in descending order")]
+        desc: bool,
+        #[arg(long, value_name = "NUM", help = "Only tasks with priority >= NUM")]
+        priority_min: Option<i64>,
+        #[arg(long, value_name = "NUM", help = "Only tasks with priority <= NUM")]
+        priority_max: Option<i64>,
+        #[arg(
+            long,
+            value_name = "ID|KEY",
+            add = ArgValueCandidates::new(complete::task_refs),
+            help = "Only direct children of the given task"
+        )]
+        parent: Option<TaskRef>,
+        #[arg(
+            long,
+            value_name = "ID|KEY",
+            add = ArgValueCandidates::new(complete::task_refs),
+            help = "Only transitive descendants of the given task"
+        )]
+        descendant_of: Option<TaskRef>,
+        #[arg(long, help = "Only tasks that have at least one child")]
+        has_children: bool,
+        #[arg(long, help = "Only open tasks whose due date has passed")]
+        overdue: bool,
+        #[arg(
+            long,
+            value_name = "TEXT",
+            action = ArgAction::Append,
+            help = "Only tasks whose title contains TEXT, case-insensitively (repeatable)"
+        )]
+        title_contains: Vec<String>,
+        #[arg(long, help = "Print per-state totals instead of tasks")]
+        count: bool,
+        #[arg(long, help = "Include descriptions in the table output")]
+        wide: bool,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Full-text search task titles and descriptions, best matches first
+    Search {
+        #[arg(value_name = "QUERY", help = "Words to search for (all must match)")]
+        query: String,
+        #[arg(
+            long,
+            value_name = "STATES",
+            value_delimiter = ',',
+            default_value = "ready,blocked,deferred,in_progress,needs_human",
+            help = "Comma-separated states to include"
+        )]
+        state: Vec<TaskState>,
+        #[arg(
+            long,
+            value_name = "LIMIT",
+            default_value_t = 20,
+            help = "Maximum number of tasks to return"
+        )]
+        limit: u64,
+    },
+    /// Show a single task by id or key
+    Show {
+        #[arg(
+            long,
+            value_name = "ID|KEY",
+            add = ArgValueCandidates::new(complete::task_refs),
+            help = "Task id or key to show"
+        )]
+        id: TaskRef,
+        #[command(flatten)]
+        output: OutputArgs,
     },
     /// Claim the highest-priority ready task and mark it in progress
-    ClaimNext,
+    ClaimNext {
+        #[arg(long, help = "If nothing is ready, block until a task can be claimed")]
+        wait: bool,
+        #[arg(
+            long,
+            value_name = "DURATION",
+            requires = "wait",
+            value_parser = parse_duration,
+            help = "Give up waiting after DURATION (e.g. 30s, 10m)"
+        )]
+        timeout: Option<Duration>,
+        #[arg(
+            long,
+            value_enum,
+            env = "PEARLS_SCHEDULE",
+            default_value_t = Schedule::Priority,
+            help = "How to choose among ready tasks"
+        )]
+        schedule: Schedule,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
     /// Add a task with a given title, description, and optional priority, parent, and child
     Add {
         #[arg(long, value_name = "TITLE", help = "Task title")]
@@ -91,27 +335,70 @@ pub enum TaskSubcommand {
         description: String,
         #[arg(
             long,
-            value_name = "OTHER_ID",
-            help = "Make this task the parent of the given task id"
+            value_name = "OTHER_ID|KEY",
+            add = ArgValueCandidates::new(complete::task_refs),
+            help = "Make this task the parent of the given task id or key"
         )]
-        parent_of: Option<u64>,
+        parent_of: Option<TaskRef>,
         #[arg(
             long,
-            value_name = "OTHER_ID",
-            help = "Make this task the child of the given task id"
+            value_name = "OTHER_ID|KEY",
+            add = ArgValueCandidates::new(complete::task_refs),
+            help = "Make this task the child of the given task id or key"
         )]
-        child_of: Option<u64>,
+        child_of: Option<TaskRef>,
         #[arg(
             long,
             value_name = "NUM",
             help = "Task priority (lower is more important)"
         )]
         priority: Option<i64>,
+        #[arg(
+            long,
+            conflicts_with = "idempotent",
+            help = "Refuse to add the task if a similar open task exists"
+        )]
+        no_duplicates: bool,
+        #[arg(
+            long,
+            help = "Return the most similar open task instead of adding a duplicate"
+        )]
+        idempotent: bool,
+        #[arg(
+            long,
+            value_name = "KEY",
+            value_parser = parse_key,
+            help = "Unique external key; if a task already has it, return that task instead"
+        )]
+        key: Option<String>,
+        #[arg(
+            long,
+            value_name = "COMMAND",
+            help = "Shell command that must succeed before `tasks close` closes the task"
+        )]
+        verify: Option<String>,
+        #[arg(
+            long,
+            value_name = "TIME",
+            help = "Keep the task deferred until TIME: a unix timestamp, a UTC date such as 2026-11-01T09:00Z, or a delay such as 2h"
+        )]
+        not_before: Option<TimeArg>,
+        #[arg(
+            long,
+            value_name = "TIME",
+            help = "When the task is due, in the same forms as --not-before"
+        )]
+        due: Option<TimeArg>,
     },
     /// Update the metadata associated with a given task ID
     UpdateMetadata {
-        #[arg(long, value_name = "ID", help = "Task id to update")]
-        id: u64,
+        #[arg(
+            long,
+            value_name = "ID|KEY",
+            add = ArgValueCandidates::new(complete::task_refs),
+            help = "Task id or key to update"
+        )]
+        id: TaskRef,
         #[arg(long, value_name = "TITLE", help = "New title (optional)")]
         title: Option<String>,
         #[arg(long, value_name = "DESC", help = "New description (optional)")]
@@ -120,16 +407,194 @@ pub enum TaskSubcommand {
         priority: Option<i64>,
         #[arg(long, value_name = "STATE", help = "New state (optional)")]
         state: Option<TaskState>,
+        #[arg(
+            long,
+            value_name = "COMMAND",
+            help = "New verify command (optional; an empty string removes it)"
+        )]
+        verify: Option<String>,
+        #[arg(
+            long,
+            value_name = "TIME",
+            help = "New start time, as for `tasks add` (optional; an empty string removes it)"
+        )]
+        not_before: Option<TimeArg>,
+        #[arg(
+            long,
+            value_name = "TIME",
+            help = "New due date, as for `tasks add` (optional; an empty string removes it)"
+        )]
+        due: Option<TimeArg>,
+    },
+    /// Defer a task so it isn't handed out for a while
+    Snooze {
+        #[arg(
+            long,
+            value_name = "ID|KEY",
+            add = ArgValueCandidates::new(complete::task_refs),
+            help = "Task id or key to snooze"
+        )]
+        id: TaskRef,
+        #[arg(
+            long = "for",
+            value_name = "DURATION",
+            value_parser = parse_delay,
+            help = "How long to defer the task, e.g. 30m or 2h"
+        )]
+        duration: Duration,
+    },
+    /// Close a task once its verify command (if any) succeeds
+    Close {
+        #[arg(
+            long,
+            value_name = "ID|KEY",
+            add = ArgValueCandidates::new(complete::task_refs),
+            help = "Task id or key to close"
+        )]
+        id: TaskRef,
+        #[arg(long, help = "Close without running the verify command")]
+        skip_verify: bool,
+        #[arg(long, help = "Close even if some acceptance criteria are unchecked")]
+        force: bool,
+    },
+    /// Record a failed attempt on an in-progress task and put it back in the queue
+    Fail {
+        #[arg(
+            long,
+            value_name = "ID|KEY",
+            add = ArgValueCandidates::new(complete::task_refs),
+            help = "Task id or key"
+        )]
+        id: TaskRef,
+        #[arg(long, value_name = "TEXT", help = "What went wrong (saved as a note)")]
+        reason: String,
+        #[arg(
+            long,
+            value_name = "N",
+            env = "PEARLS_MAX_ATTEMPTS",
+            default_value_t = 3,
+            value_parser = clap::value_parser!(i64).range(1..),
+            help = "Park the task as needs_human once it has failed this many times"
+        )]
+        max_attempts: i64,
+        #[arg(
+            long,
+            value_name = "DURATION",
+            env = "PEARLS_RETRY_BACKOFF",
+            value_parser = parse_duration,
+            help = "Keep the task unclaimable for DURATION, doubled for every earlier failure"
+        )]
+        backoff: Option<Duration>,
+    },
+    /// Add or check off a task's acceptance criteria
+    Criteria {
+        #[command(subcommand)]
+        command: CriteriaCommand,
+    },
+    /// List the notes recorded on a task, oldest first
+    Notes {
+        #[arg(
+            long,
+            value_name = "ID|KEY",
+            add = ArgValueCandidates::new(complete::task_refs),
+            help = "Task id or key"
+        )]
+        id: TaskRef,
     },
     /// Update child dependency relationships for a given task ID
     UpdateDependency {
-        #[arg(long, value_name = "ID", help = "Task id to update")]
-        id: u64,
-        #[arg(long, value_name = "CHILD_ID", action = ArgAction::Append, help = "Add child dependency (repeatable)")]
-        add_child: Vec<u64>,
-        #[arg(long, value_name = "CHILD_ID", action = ArgAction::Append, help = "Remove child dependency (repeatable)")]
-        remove_child: Vec<u64>,
+        #[arg(
+            long,
+            value_name = "ID|KEY",
+            add = ArgValueCandidates::new(complete::task_refs),
+            help = "Task id or key to update"
+        )]
+        id: TaskRef,
+        #[arg(
+            long,
+            value_name = "CHILD_ID|KEY",
+            action = ArgAction::Append,
+            add = ArgValueCandidates::new(complete::task_refs),
+            help = "Add child dependency (repeatable)"
+        )]
+        add_child: Vec<TaskRef>,
+        #[arg(
+            long,
+            value_name = "CHILD_ID|KEY",
+            action = ArgAction::Append,
+            add = ArgValueCandidates::new(complete::task_refs),
+            help = "Remove child dependency (repeatable)"
+        )]
+        remove_child: Vec<TaskRef>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CriteriaCommand {
+    /// Append acceptance criteria to a task
+    Add {
+        #[arg(
+            long,
+            value_name = "ID|KEY",
+            add = ArgValueCandidates::new(complete::task_refs),
+            help = "Task id or key"
+        )]
+        id: TaskRef,
+        #[arg(
+            long,
+            value_name = "TEXT",
+            action = ArgAction::Append,
+            required = true,
+            help = "Criterion text (repeatable)"
+        )]
+        text: Vec<String>,
     },
+    /// Mark acceptance criteria as met
+    Check {
+        #[arg(
+            long,
+            value_name = "ID|KEY",
+            add = ArgValueCandidates::new(complete::task_refs),
+            help = "Task id or key"
+        )]
+        id: TaskRef,
+        #[arg(
+            long,
+            value_name = "N",
+            action = ArgAction::Append,
+            required_unless_present = "all",
+            value_parser = clap::value_parser!(usize),
+            help = "Position of the criterion, starting at 1 (repeatable)"
+        )]
+        item: Vec<usize>,
+        #[arg(long, conflicts_with = "item", help = "Check every criterion")]
+        all: bool,
+        #[arg(long, help = "Clear the check mark instead of setting it")]
+        uncheck: bool,
+    },
+}
+
+/// How `claim-next` picks among ready tasks.
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub enum Schedule {
+    /// Lowest priority number first, then lowest id.
+    #[default]
+    Priority,
+    /// Overdue tasks first, then tasks due within a day, then by priority.
+    Deadline,
+    /// Lowest effective priority first: a task inherits the priority of the
+    /// most important open task waiting on it.
+    Inherited,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub enum TaskSort {
+    Priority,
+    Id,
+    Created,
+    Updated,
 }
 
 #[derive(Debug, Clone, Copy, ValueEnum)]
@@ -137,6 +602,217 @@ pub enum TaskSubcommand {
 pub enum TaskState {
     Ready,
     Blocked,
+    Deferred,
     InProgress,
+    NeedsHuman,
     Closed,
 }
+
+/// A task named on the command line, either by numeric id or by its key.
+#[derive(Debug, Clone)]
+pub enum TaskRef {
+    Id(i64),
+    Key(String),
+}
+
+impl FromStr for TaskRef {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        match value.parse::<i64>() {
+            Ok(id) if id > 0 => return Ok(TaskRef::Id(id)),
+            Ok(id) => return Err(format!("task id {id} must be positive")),
+            Err(_) if !value.is_empty() && value.chars().all(|ch| ch.is_ascii_digit()) => {
+                return Err(format!("task id {value} is too large"));
+            }
+            Err(_) => {}
+        }
+        parse_key(value).map(TaskRef::Key)
+    }
+}
+
+impl fmt::Display for TaskRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskRef::Id(id) => write!(f, "#{id}"),
+            TaskRef::Key(key) => write!(f, "{key}"),
+        }
+    }
+}
+
+/// A point in time on the command line: a unix timestamp, a UTC date or
+/// date-time (`2026-11-01`, `2026-11-01T09:30Z`), or a delay from now in the
+/// units [`parse_duration`] accepts. An empty string means no time at all.
+#[derive(Debug, Clone, Copy)]
+pub enum TimeArg {
+    Unset,
+    At(i64),
+    In(Duration),
+}
+
+impl TimeArg {
+    /// The unix timestamp this names, with delays counted from now.
+    pub fn resolve(&self) -> Option<i64> {
+        match self {
+            TimeArg::Unset => None,
+            TimeArg::At(at) => Some(*at),
+            // `parse_delay` already turned away delays that don't fit.
+            TimeArg::In(delay) => Some(timestamp_in(*delay).unwrap_or(i64::MAX)),
+        }
+    }
+}
+
+/// The unix timestamp `delay` from now, unless it is out of range.
+fn timestamp_in(delay: Duration) -> Option<i64> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    now.checked_add(delay)
+        .and_then(|at| i64::try_from(at.as_secs()).ok())
+}
+
+impl FromStr for TimeArg {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Ok(TimeArg::Unset);
+        }
+        if let Ok(at) = value.parse::<i64>() {
+            return Ok(TimeArg::At(at));
+        }
+        if let Some(at) = parse_utc(value) {
+            return Ok(TimeArg::At(at));
+        }
+        if parse_duration(value).is_err() {
+            return Err(format!(
+                "invalid time {value:?} (expected a unix timestamp, a date such as 2026-11-01T09:00Z, or a delay such as 2h)"
+            ));
+        }
+        parse_delay(value).map(TimeArg::In)
+    }
+}
+
+/// Parses `YYYY-MM-DD`, optionally followed by `THH:MM[:SS]` and `Z`, as UTC.
+fn parse_utc(value: &str) -> Option<i64> {
+    let value = value.strip_suffix('Z').unwrap_or(value);
+    let (date, time) = match value.split_once(['T', ' ']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (value, None),
+    };
+
+    let mut date = date.splitn(3, '-');
+    let year: i64 = date.next()?.parse().ok()?;
+    let month: i64 = date.next()?.parse().ok()?;
+    let day: i64 = date.next()?.parse().ok()?;
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days_in_month = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return None,
+    };
+    if !(1..=days_in_month).contains(&day) {
+        return None;
+    }
+
+    let mut seconds = 0;
+    if let Some(time) = time {
+        let mut parts = time.splitn(3, ':');
+        let hour: i64 = parts.next()?.parse().ok()?;
+        let minute: i64 = parts.next()?.parse().ok()?;
+        let second: i64 = parts.next().map_or(Some(0), |part| part.parse().ok())?;
+        if hour > 23 || minute > 59 || second > 59 {
+            return None;
+        }
+        seconds = hour * 3600 + minute * 60 + second;
+    }
+
+    // Days since 1970-01-01 in the proleptic Gregorian calendar.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+    Some(days * 86_400 + seconds)
+}
+
+/// Output shaping shared by commands that print tasks.
+#[derive(Debug, Args)]
+pub struct OutputArgs {
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        conflicts_with = "fields",
+        help = "Print each task with a template such as '{id}\\t{title}' (fields: id, title, desc, priority, state, key, created_at, updated_at, parents, children)"
+    )]
+    pub format: Option<Template>,
+    #[arg(
+        long,
+        value_name = "FIELDS",
+        value_delimiter = ',',
+        value_parser = output::parse_field,
+        help = "Comma-separated fields to keep in JSON output (tab-separated in text output)"
+    )]
+    pub fields: Vec<&'static str>,
+}
+
+impl OutputArgs {
+    /// The template to print tasks with, if text output was customized.
+    pub fn template(&self, json: bool) -> Option<Template> {
+        match &self.format {
+            Some(template) => Some(template.clone()),
+            None if !json && !self.fields.is_empty() => Some(Template::from_fields(&self.fields)),
+            None => None,
+        }
+    }
+}
+
+/// Parses durations such as `500ms`, `30s`, `10m`, `2h`, or `1d`. A bare
+/// number is taken as seconds.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|ch: char| !ch.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("invalid duration {value:?} (expected e.g. 30s, 10m, 2h)"))?;
+    let seconds = match unit {
+        "ms" => return Ok(Duration::from_millis(amount)),
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(format!("unknown duration unit {unit:?} in {value:?}")),
+    };
+    Ok(Duration::from_secs(amount.saturating_mul(seconds)))
+}
+
+/// A [`parse_duration`] delay from now, refused if the time it names can't
+/// be stored.
+pub fn parse_delay(value: &str) -> Result<Duration, String> {
+    let delay = parse_duration(value)?;
+    match timestamp_in(delay) {
+        Some(_) => Ok(delay),
+        None => Err(format!("delay {:?} is too long", value.trim())),
+    }
+}
+
+/// Keys share the argument space with numeric ids, so they must not be
+/// all digits.
+fn parse_key(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err("task key cannot be empty".to_string());
+    }
+    if value.chars().all(|ch| ch.is_ascii_digit()) {
+        return Err(format!("task key {value:?} cannot be all digits"));
+    }
+    Ok(value.to_string())
+}
@@ -0,0 +1,90 @@
+use std::path::PathBuf;
+
+use clap_complete::CompletionCandidate;
+use clap_complete::env::Shells;
+
+use crate::cli::{CompletionShell, TaskState};
+use crate::db;
+
+/// Environment variable the registration scripts set when asking `pearls`
+/// for candidates.
+pub const COMPLETE_VAR: &str = "COMPLETE";
+
+/// Writes the script that hooks `pearls` into `shell`'s completion. The
+/// script calls back into this binary, so candidates (including task ids)
+/// are always computed by the installed version.
+pub fn write_registration(shell: CompletionShell) -> std::io::Result<()> {
+    let name = match shell {
+        CompletionShell::Bash => "bash",
+        CompletionShell::Zsh => "zsh",
+        CompletionShell::Fish => "fish",
+    };
+    let shells = Shells::builtins();
+    let completer = shells
+        .completer(name)
+        .ok_or_else(|| std::io::Error::other(format!("unsupported shell {name}")))?;
+    let program = std::env::args()
+        .next()
+        .unwrap_or_else(|| "pearls".to_string());
+    completer.write_registration(
+        COMPLETE_VAR,
+        "pearls",
+        "pearls",
+        &program,
+        &mut std::io::stdout(),
+    )
+}
+
+/// Open tasks as candidates for id/key arguments: each id with its title as
+/// help text, plus any task keys. Completion cannot see `--db`, so this reads
+/// `PEARLS_DB` or `./pearls.db`. The database is only read, never created
+/// or migrated, so nothing is offered unless this version already set it up.
+pub fn task_refs() -> Vec<CompletionCandidate> {
+    let db_path = std::env::var_os("PEARLS_DB")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("pearls.db"));
+    if !db_path.exists() {
+        return Vec::new();
+    }
+    let Ok(runtime) = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    else {
+        return Vec::new();
+    };
+
+    let tasks = runtime.block_on(async {
+        let conn = db::conn::connect_read_only(&db_path).await?;
+        db::tasks::list_tasks(
+            &conn,
+            &[
+                TaskState::Ready,
+                TaskState::Blocked,
+                TaskState::Deferred,
+                TaskState::InProgress,
+                TaskState::NeedsHuman,
+            ],
+        )
+        .await
+    });
+    let Ok(tasks) = tasks else {
+        return Vec::new();
+    };
+
+    let mut candidates = Vec::new();
+    for task in &tasks {
+        let title = task.title.clone().unwrap_or_default();
+        candidates.push(
+            CompletionCandidate::new(task.id.to_string())
+                .help(Some(format!("[{}] {title}", task.state).into())),
+        );
+    }
+    for task in &tasks {
+        if let Some(key) = &task.key {
+            candidates.push(CompletionCandidate::new(key).help(Some(
+                format!("#{} {}", task.id, task.title.as_deref().unwrap_or("")).into(),
+            )));
+        }
+    }
+    candidates
+}
@@ -1,11 +1,56 @@
+use std::collections::HashSet;
 use std::path::Path;
 
 use sea_orm_migration::MigratorTrait;
-use sea_orm_migration::sea_orm::{Database, DatabaseConnection, DbErr};
+use sea_orm_migration::sea_orm::{
+    ConnectOptions, ConnectionTrait, Database, DatabaseConnection, DbBackend, DbErr, Statement,
+};
 
 use crate::db::migration::Migrator;
 
 pub async fn connect(path: &Path) -> Result<DatabaseConnection, DbErr> {
+    connect_with(path, |_| {}).await
+}
+
+/// Like [`connect`], but every query runs on the same SQLite connection, so
+/// per-connection pragmas such as `data_version` can be compared over time.
+pub async fn connect_single(path: &Path) -> Result<DatabaseConnection, DbErr> {
+    connect_with(path, |options| {
+        options.min_connections(1).max_connections(1);
+    })
+    .await
+}
+
+/// Opens an existing database for reading only: nothing is created,
+/// migrated or locked. Fails unless every migration this build knows about
+/// has already been applied.
+pub async fn connect_read_only(path: &Path) -> Result<DatabaseConnection, DbErr> {
+    let conn = Database::connect(format!("{}?mode=ro", url(path))).await?;
+    let applied = conn
+        .query_all(Statement::from_string(
+            DbBackend::Sqlite,
+            "SELECT version FROM seaql_migrations",
+        ))
+        .await?
+        .iter()
+        .map(|row| row.try_get_by_index::<String>(0))
+        .collect::<Result<HashSet<_>, _>>()?;
+    if let Some(missing) = Migrator::migrations()
+        .iter()
+        .find(|migration| !applied.contains(migration.name()))
+    {
+        return Err(DbErr::Custom(format!(
+            "database predates migration {}",
+            missing.name()
+        )));
+    }
+    Ok(conn)
+}
+
+async fn connect_with(
+    path: &Path,
+    configure: impl FnOnce(&mut ConnectOptions),
+) -> Result<DatabaseConnection, DbErr> {
     if let Some(parent) = path.parent()
         && let Err(err) = std::fs::create_dir_all(parent)
     {
@@ -15,17 +60,23 @@ pub async fn connect(path: &Path) -> Result<DatabaseConnection, DbErr> {
         )));
     }
 
-    let mut url = if path.is_absolute() {
+    let mut url = url(path);
+    if !url.contains('?') {
+        url.push_str("?mode=rwc");
+    }
+    let mut options = ConnectOptions::new(url);
+    configure(&mut options);
+    let conn = Database::connect(options).await?;
+    Migrator::up(&conn, None).await?;
+    Ok(conn)
+}
+
+fn url(path: &Path) -> String {
+    if path.is_absolute() {
         let path_str = path.display().to_string();
         let trimmed = path_str.strip_prefix('/').unwrap_or(&path_str);
         format!("sqlite:///{}", trimmed)
     } else {
         format!("sqlite://{}", path.display())
-    };
-    if !url.contains('?') {
-        url.push_str("?mode=rwc");
     }
-    let conn = Database::connect(&url).await?;
-    Migrator::up(&conn, None).await?;
-    Ok(conn)
 }
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+use sea_orm_migration::prelude::ConnectionTrait;
+use sea_orm_migration::sea_orm::{DbBackend, DbErr, QueryResult, Statement};
+use sea_query::{Expr, Iden, InsertStatement, Order, Query, SimpleExpr, SqliteQueryBuilder};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Iden)]
+enum Criterion {
+    Table,
+    Id,
+    TaskId,
+    Text,
+    Checked,
+}
+
+/// One acceptance criterion. Criteria are addressed by their 1-based
+/// position in the task's list.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct CriterionRow {
+    pub text: String,
+    #[serde(default)]
+    pub checked: bool,
+}
+
+/// Appends criteria to the end of a task's list.
+pub async fn add_criteria<C: ConnectionTrait>(
+    conn: &C,
+    task_id: i64,
+    criteria: &[CriterionRow],
+) -> Result<(), DbErr> {
+    for criterion in criteria {
+        let mut insert = InsertStatement::new();
+        insert
+            .into_table(Criterion::Table)
+            .columns([Criterion::TaskId, Criterion::Text, Criterion::Checked])
+            .values([
+                Expr::val(task_id).into(),
+                Expr::val(criterion.text.as_str()).into(),
+                Expr::val(criterion.checked).into(),
+            ])
+            .map_err(|err| DbErr::Custom(err.to_string()))?;
+        let (sql, values) = insert.build(SqliteQueryBuilder);
+        conn.execute(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            sql,
+            values,
+        ))
+        .await?;
+    }
+    Ok(())
+}
+
+/// Marks the criteria at the given 1-based positions as checked (or not).
+/// Fails without changing anything if a position is out of range.
+pub async fn set_checked<C: ConnectionTrait>(
+    conn: &C,
+    task_id: i64,
+    items: &[usize],
+    checked: bool,
+) -> Result<(), DbErr> {
+    let ids = criterion_ids(conn, task_id).await?;
+    let mut selected: Vec<SimpleExpr> = Vec::with_capacity(items.len());
+    for item in items {
+        let id = item
+            .checked_sub(1)
+            .and_then(|index| ids.get(index))
+            .ok_or_else(|| {
+                DbErr::Custom(format!(
+                    "task {task_id} has no criterion {item} (it has {})",
+                    ids.len()
+                ))
+            })?;
+        selected.push(Expr::val(*id).into());
+    }
+    if selected.is_empty() {
+        return Ok(());
+    }
+
+    let (sql, values) = Query::update()
+        .table(Criterion::Table)
+        .value(Criterion::Checked, checked)
+        .and_where(Expr::col(Criterion::Id).is_in(selected))
+        .build(SqliteQueryBuilder);
+    conn.execute(Statement::from_sql_and_values(
+        DbBackend::Sqlite,
+        sql,
+        values,
+    ))
+    .await?;
+    Ok(())
+}
+
+/// Criteria of each task in `task_ids`, in list order. Tasks without
+/// criteria are absent from the map.
+pub async fn load_criteria<C: ConnectionTrait>(
+    conn: &C,
+    task_ids: &[i64],
+) -> Result<HashMap<i64, Vec<CriterionRow>>, DbErr> {
+    let mut by_task: HashMap<i64, Vec<CriterionRow>> = HashMap::new();
+    if task_ids.is_empty() {
+        return Ok(by_task);
+    }
+
+    let id_exprs: Vec<SimpleExpr> = task_ids.iter().map(|id| Expr::val(*id).into()).collect();
+    let (sql, values) = Query::select()
+        .columns([Criterion::TaskId, Criterion::Text, Criterion::Checked])
+        .from(Criterion::Table)
+        .and_where(Expr::col(Criterion::TaskId).is_in(id_exprs))
+        .order_by(Criterion::Id, Order::Asc)
+        .build(SqliteQueryBuilder);
+    let rows: Vec<QueryResult> = conn
+        .query_all(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            sql,
+            values,
+        ))
+        .await?;
+
+    for row in rows {
+        let task_id: i64 = row.try_get_by_index(0)?;
+        by_task.entry(task_id).or_default().push(CriterionRow {
+            text: row.try_get_by_index(1)?,
+            checked: row.try_get_by_index(2)?,
+        });
+    }
+    Ok(by_task)
+}
+
+/// Replaces a task's whole list, e.g. when a sync file edits it.
+pub async fn replace_criteria<C: ConnectionTrait>(
+    conn: &C,
+    task_id: i64,
+    criteria: &[CriterionRow],
+) -> Result<(), DbErr> {
+    let (sql, values) = Query::delete()
+        .from_table(Criterion::Table)
+        .and_where(Expr::col(Criterion::TaskId).eq(task_id))
+        .build(SqliteQueryBuilder);
+    conn.execute(Statement::from_sql_and_values(
+        DbBackend::Sqlite,
+        sql,
+        values,
+    ))
+    .await?;
+    add_criteria(conn, task_id, criteria).await
+}
+
+async fn criterion_ids<C: ConnectionTrait>(conn: &C, task_id: i64) -> Result<Vec<i64>, DbErr> {
+    let (sql, values) = Query::select()
+        .column(Criterion::Id)
+        .from(Criterion::Table)
+        .and_where(Expr::col(Criterion::TaskId).eq(task_id))
+        .order_by(Criterion::Id, Order::Asc)
+        .build(SqliteQueryBuilder);
+    let rows: Vec<QueryResult> = conn
+        .query_all(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            sql,
+            values,
+        ))
+        .await?;
+    rows.iter().map(|row| row.try_get_by_index(0)).collect()
+}
@@ -0,0 +1,533 @@
+use std::collections::{BTreeMap, HashSet};
+
+use sea_orm_migration::prelude::ConnectionTrait;
+use sea_orm_migration::sea_orm::{
+    DatabaseConnection, DbBackend, DbErr, QueryResult, Statement, TransactionTrait,
+};
+use sea_query::{Expr, InsertStatement, OnConflict, Order, Query, SimpleExpr, SqliteQueryBuilder};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::TaskState;
+use crate::db::criteria::{self, CriterionRow};
+use crate::db::notes;
+use crate::db::tasks::{Dependency, Task};
+
+/// Format version written to every dump. Bump when the layout changes in a
+/// way older readers cannot ignore.
+pub const DUMP_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Dump {
+    pub version: u32,
+    pub tasks: Vec<DumpTask>,
+    #[serde(default)]
+    pub dependencies: Vec<DumpDependency>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<DumpNote>,
+}
+
+/// A task exactly as stored, with its persisted (not derived) state.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DumpTask {
+    pub id: i64,
+    pub title: Option<String>,
+    pub desc: Option<String>,
+    pub priority: i64,
+    pub state: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verify: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub criteria: Vec<CriterionRow>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub attempts: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_before: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_at: Option<i64>,
+    /// Unix timestamps; zero (or missing) means "now" on import.
+    #[serde(default)]
+    pub created_at: i64,
+    #[serde(default)]
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DumpDependency {
+    pub parent_id: i64,
+    pub child_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DumpNote {
+    pub task_id: i64,
+    pub body: String,
+    #[serde(default)]
+    pub created_at: i64,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ImportSummary {
+    pub tasks: usize,
+    pub dependencies: usize,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub id_map: BTreeMap<i64, i64>,
+    /// New task ids keyed by identifiers from a foreign format (e.g. beads ids).
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub source_ids: BTreeMap<String, i64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+pub async fn export(conn: &DatabaseConnection) -> Result<Dump, DbErr> {
+    let (sql, values) = Query::select()
+        .columns([
+            Task::Id,
+            Task::Title,
+            Task::Desc,
+            Task::Priority,
+            Task::State,
+            Task::Key,
+            Task::CreatedAt,
+            Task::UpdatedAt,
+            Task::Verify,
+            Task::Attempts,
+            Task::NotBefore,
+            Task::DueAt,
+        ])
+        .from(Task::Table)
+        .order_by(Task::Id, Order::Asc)
+        .build(SqliteQueryBuilder);
+    let rows: Vec<QueryResult> = conn
+        .query_all(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            sql,
+            values,
+        ))
+        .await?;
+
+    let mut tasks = Vec::with_capacity(rows.len());
+    for row in rows {
+        tasks.push(DumpTask {
+            id: row.try_get_by_index(0)?,
+            title: row.try_get_by_index(1)?,
+            desc: row.try_get_by_index(2)?,
+            priority: row.try_get_by_index(3)?,
+            state: row.try_get_by_index(4)?,
+            key: row.try_get_by_index(5)?,
+            created_at: row.try_get_by_index(6)?,
+            updated_at: row.try_get_by_index(7)?,
+            verify: row.try_get_by_index(8)?,
+            attempts: row.try_get_by_index(9)?,
+            not_before: row.try_get_by_index(10)?,
+            due_at: row.try_get_by_index(11)?,
+            criteria: Vec::new(),
+        });
+    }
+    let ids: Vec<i64> = tasks.iter().map(|task| task.id).collect();
+    let mut criteria = criteria::load_criteria(conn, &ids).await?;
+    for task in &mut tasks {
+        task.criteria = criteria.remove(&task.id).unwrap_or_default();
+    }
+
+    let (sql, values) = Query::select()
+        .columns([Dependency::ParentId, Dependency::ChildId])
+        .from(Dependency::Table)
+        .order_by(Dependency::ParentId, Order::Asc)
+        .order_by(Dependency::ChildId, Order::Asc)
+        .build(SqliteQueryBuilder);
+    let rows: Vec<QueryResult> = conn
+        .query_all(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            sql,
+            values,
+        ))
+        .await?;
+
+    let mut dependencies = Vec::with_capacity(rows.len());
+    for row in rows {
+        dependencies.push(DumpDependency {
+            parent_id: row.try_get_by_index(0)?,
+            child_id: row.try_get_by_index(1)?,
+        });
+    }
+
+    let notes = notes::all_notes(conn)
+        .await?
+        .into_iter()
+        .map(|note| DumpNote {
+            task_id: note.task_id,
+            body: note.body,
+            created_at: note.created_at,
+        })
+        .collect();
+
+    Ok(Dump {
+        version: DUMP_VERSION,
+        tasks,
+        dependencies,
+        notes,
+    })
+}
+
+/// Restores a dump inside a single transaction. Without `remap_ids` the
+/// original ids are kept and any collision with an existing task aborts the
+/// import; with it every task gets a fresh id and dependencies are rewritten.
+pub async fn import(
+    conn: &DatabaseConnection,
+    dump: &Dump,
+    remap_ids: bool,
+) -> Result<ImportSummary, DbErr> {
+    validate(dump)?;
+
+    let txn = conn.begin().await?;
+
+    if !remap_ids {
+        let ids: Vec<i64> = dump.tasks.iter().map(|task| task.id).collect();
+        let existing = existing_ids(&txn, &ids).await?;
+        if !existing.is_empty() {
+            let joined = existing
+                .iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+            return Err(DbErr::Custom(format!(
+                "task ids already exist: {joined} (use --remap-ids to import alongside them)"
+            )));
+        }
+    }
+
+    // Keys stay as they are even with --remap-ids, so they must be free.
+    let keys: Vec<&str> = dump
+        .tasks
+        .iter()
+        .filter_map(|task| task.key.as_deref())
+        .collect();
+    let taken = existing_keys(&txn, &keys).await?;
+    if !taken.is_empty() {
+        let joined = taken
+            .iter()
+            .map(|(key, id)| format!("{key} (#{id})"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        return Err(DbErr::Custom(format!(
+            "task keys already exist: {joined} (rename or remove them in the file to import it)"
+        )));
+    }
+
+    let mut id_map = BTreeMap::new();
+    for task in &dump.tasks {
+        let new_id = insert_task(&txn, task, !remap_ids).await?;
+        id_map.insert(task.id, new_id);
+    }
+
+    for dep in &dump.dependencies {
+        let mut insert = InsertStatement::new();
+        insert
+            .into_table(Dependency::Table)
+            .columns([Dependency::ParentId, Dependency::ChildId])
+            .values([
+                Expr::val(id_map[&dep.parent_id]).into(),
+                Expr::val(id_map[&dep.child_id]).into(),
+            ])
+            .map_err(|err| DbErr::Custom(err.to_string()))?
+            .on_conflict(
+                OnConflict::columns([Dependency::ParentId, Dependency::ChildId])
+                    .do_nothing()
+                    .to_owned(),
+            );
+        let (sql, values) = insert.build(SqliteQueryBuilder);
+        txn.execute(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            sql,
+            values,
+        ))
+        .await?;
+    }
+
+    for note in &dump.notes {
+        let created_at = (note.created_at != 0).then_some(note.created_at);
+        notes::add_note(&txn, id_map[&note.task_id], &note.body, created_at).await?;
+    }
+
+    txn.commit().await?;
+
+    if !remap_ids {
+        id_map.clear();
+    }
+    Ok(ImportSummary {
+        tasks: dump.tasks.len(),
+        dependencies: dump.dependencies.len(),
+        id_map,
+        source_ids: BTreeMap::new(),
+        warnings: Vec::new(),
+    })
+}
+
+fn is_zero(value: &i64) -> bool {
+    *value == 0
+}
+
+fn validate(dump: &Dump) -> Result<(), DbErr> {
+    if dump.version > DUMP_VERSION {
+        return Err(DbErr::Custom(format!(
+            "dump version {} is newer than supported version {DUMP_VERSION}",
+            dump.version
+        )));
+    }
+
+    let mut ids = HashSet::new();
+    let mut keys = HashSet::new();
+    for task in &dump.tasks {
+        if !ids.insert(task.id) {
+            return Err(DbErr::Custom(format!(
+                "duplicate task id {} in dump",
+                task.id
+            )));
+        }
+        if let Some(key) = &task.key
+            && !keys.insert(key)
+        {
+            return Err(DbErr::Custom(format!("duplicate task key {key:?} in dump")));
+        }
+        match TaskState::parse(&task.state) {
+            None => {
+                return Err(DbErr::Custom(format!(
+                    "task {} has unknown state {:?}",
+                    task.id, task.state
+                )));
+            }
+            Some(state) if state.is_derived() => {
+                return Err(DbErr::Custom(format!(
+                    "task {} has state {:?}, which is derived from parents and not-before times and can't be imported",
+                    task.id, task.state
+                )));
+            }
+            Some(_) => {}
+        }
+    }
+
+    for dep in &dump.dependencies {
+        for id in [dep.parent_id, dep.child_id] {
+            if !ids.contains(&id) {
+                return Err(DbErr::Custom(format!(
+                    "dependency {} -> {} references task {id} missing from dump",
+                    dep.parent_id, dep.child_id
+                )));
+            }
+        }
+    }
+
+    for note in &dump.notes {
+        if !ids.contains(&note.task_id) {
+            return Err(DbErr::Custom(format!(
+                "note references task {} missing from dump",
+                note.task_id
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+async fn existing_ids<C: ConnectionTrait>(conn: &C, ids: &[i64]) -> Result<Vec<i64>, DbErr> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let id_exprs: Vec<SimpleExpr> = ids.iter().map(|id| Expr::val(*id).into()).collect();
+    let (sql, values) = Query::select()
+        .column(Task::Id)
+        .from(Task::Table)
+        .and_where(Expr::col(Task::Id).is_in(id_exprs))
+        .order_by(Task::Id, Order::Asc)
+        .build(SqliteQueryBuilder);
+    let rows: Vec<QueryResult> = conn
+        .query_all(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            sql,
+            values,
+        ))
+        .await?;
+
+    rows.iter().map(|row| row.try_get_by_index(0)).collect()
+}
+
+/// Keys among `keys` that are already taken, with the task holding each.
+async fn existing_keys<C: ConnectionTrait>(
+    conn: &C,
+    keys: &[&str],
+) -> Result<Vec<(String, i64)>, DbErr> {
+    if keys.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let key_exprs: Vec<SimpleExpr> = keys.iter().map(|key| Expr::val(*key).into()).collect();
+    let (sql, values) = Query::select()
+        .columns([Task::Key, Task::Id])
+        .from(Task::Table)
+        .and_where(Expr::col(Task::Key).is_in(key_exprs))
+        .order_by(Task::Id, Order::Asc)
+        .build(SqliteQueryBuilder);
+    let rows: Vec<QueryResult> = conn
+        .query_all(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            sql,
+            values,
+        ))
+        .await?;
+
+    rows.iter()
+        .map(|row| Ok((row.try_get_by_index(0)?, row.try_get_by_index(1)?)))
+        .collect()
+}
+
+pub(crate) async fn insert_task<C: ConnectionTrait>(
+    conn: &C,
+    task: &DumpTask,
+    keep_id: bool,
+) -> Result<i64, DbErr> {
+    let mut columns = vec![
+        Task::Title,
+        Task::Desc,
+        Task::Priority,
+        Task::State,
+        Task::Key,
+        Task::CreatedAt,
+        Task::UpdatedAt,
+        Task::Verify,
+        Task::Attempts,
+        Task::NotBefore,
+        Task::DueAt,
+    ];
+    let mut values: Vec<SimpleExpr> = vec![
+        Expr::val(task.title.clone()).into(),
+        Expr::val(task.desc.clone()).into(),
+        Expr::val(task.priority).into(),
+        Expr::val(task.state.as_str()).into(),
+        Expr::val(task.key.clone()).into(),
+        Expr::val(task.created_at).into(),
+        Expr::val(task.updated_at).into(),
+        Expr::val(task.verify.clone()).into(),
+        Expr::val(task.attempts).into(),
+        Expr::val(task.not_before).into(),
+        Expr::val(task.due_at).into(),
+    ];
+    if keep_id {
+        columns.push(Task::Id);
+        values.push(Expr::val(task.id).into());
+    }
+
+    let mut insert = InsertStatement::new();
+    insert
+        .into_table(Task::Table)
+        .columns(columns)
+        .values(values)
+        .map_err(|err| DbErr::Custom(err.to_string()))?
+        .returning_col(Task::Id);
+
+    let (sql, values) = insert.build(SqliteQueryBuilder);
+    let row = conn
+        .query_one(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            sql,
+            values,
+        ))
+        .await?
+        .ok_or_else(|| DbErr::Custom("failed to read inserted task id".to_string()))?;
+    let id = row.try_get_by_index(0)?;
+    criteria::add_criteria(conn, id, &task.criteria).await?;
+    Ok(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{conn, tasks};
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn export_then_import_round_trips_and_remaps() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let source = conn::connect(&temp.path().join("source.db"))
+            .await
+            .expect("connect source");
+
+        let parent = tasks::add_task(&source, "parent", "p", Some(3), None)
+            .await
+            .expect("add parent");
+        let child = tasks::add_task(&source, "child", "c", None, Some("parser"))
+            .await
+            .expect("add child");
+        tasks::add_dependency(&source, parent.id, child.id)
+            .await
+            .expect("add dependency");
+        tasks::update_metadata(
+            &source,
+            parent.id,
+            None,
+            None,
+            None,
+            Some(TaskState::Closed),
+        )
+        .await
+        .expect("close parent");
+        tasks::set_verify(&source, child.id, Some("cargo test"))
+            .await
+            .expect("set verify");
+        notes::add_note(&source, child.id, "verify failed", None)
+            .await
+            .expect("add note");
+
+        let dump = export(&source).await.expect("export");
+        assert_eq!(dump.tasks.len(), 2);
+        assert_eq!(dump.dependencies.len(), 1);
+        assert_eq!(dump.notes.len(), 1);
+
+        let target = conn::connect(&temp.path().join("target.db"))
+            .await
+            .expect("connect target");
+        let summary = import(&target, &dump, false).await.expect("import");
+        assert_eq!(summary.tasks, 2);
+        assert!(summary.id_map.is_empty());
+
+        let restored = tasks::get_task_by_id(&target, child.id)
+            .await
+            .expect("load child");
+        assert_eq!(restored.parents, vec![parent.id]);
+        assert_eq!(restored.state, "ready");
+        assert_eq!(restored.verify.as_deref(), Some("cargo test"));
+
+        let err = import(&target, &dump, false)
+            .await
+            .expect_err("ids collide");
+        assert!(err.to_string().contains("--remap-ids"));
+        let err = import(&target, &dump, true)
+            .await
+            .expect_err("keys collide");
+        assert!(
+            err.to_string()
+                .contains(&format!("task keys already exist: parser (#{})", child.id))
+        );
+
+        let mut dump = dump;
+        for task in &mut dump.tasks {
+            task.key = None;
+        }
+        let summary = import(&target, &dump, true).await.expect("remap import");
+        let new_parent = summary.id_map[&parent.id];
+        let new_child = summary.id_map[&child.id];
+        assert_ne!(new_child, child.id);
+        let remapped = tasks::get_task_by_id(&target, new_child)
+            .await
+            .expect("load remapped child");
+        assert_eq!(remapped.parents, vec![new_parent]);
+        let notes = notes::list_notes(&target, new_child)
+            .await
+            .expect("list remapped notes");
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].body, "verify failed");
+        assert_eq!(notes[0].created_at, dump.notes[0].created_at);
+    }
+}
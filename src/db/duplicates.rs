@@ -0,0 +1,150 @@
+use std::collections::HashSet;
+
+use sea_orm_migration::sea_orm::{DatabaseConnection, DbErr};
+use serde::Serialize;
+
+use crate::cli::TaskState;
+use crate::db::tasks::{self, TaskRow};
+
+/// Minimum trigram similarity (0..=1) over title and description for two
+/// tasks to be reported as likely duplicates.
+pub const SIMILARITY_THRESHOLD: f64 = 0.6;
+
+#[derive(Debug, Serialize)]
+pub struct DuplicateMatch {
+    #[serde(flatten)]
+    pub task: TaskRow,
+    pub similarity: f64,
+}
+
+/// Finds open tasks that look like the task about to be added: either the
+/// normalized titles are identical or the title+description trigram
+/// similarity reaches [`SIMILARITY_THRESHOLD`]. Best matches come first.
+pub async fn find_duplicates(
+    conn: &DatabaseConnection,
+    title: &str,
+    desc: &str,
+) -> Result<Vec<DuplicateMatch>, DbErr> {
+    let open = tasks::list_tasks(
+        conn,
+        &[
+            TaskState::Ready,
+            TaskState::Blocked,
+            TaskState::Deferred,
+            TaskState::InProgress,
+            TaskState::NeedsHuman,
+        ],
+    )
+    .await?;
+
+    let title_key = normalize(title);
+    let candidate = trigrams(&format!("{title} {desc}"));
+    let mut matches: Vec<DuplicateMatch> = open
+        .into_iter()
+        .filter_map(|task| {
+            let other_title = task.title.as_deref().unwrap_or("");
+            let other_desc = task.desc.as_deref().unwrap_or("");
+            let similarity = if !title_key.is_empty() && normalize(other_title) == title_key {
+                1.0
+            } else {
+                jaccard(
+                    &candidate,
+                    &trigrams(&format!("{other_title} {other_desc}")),
+                )
+            };
+            (similarity >= SIMILARITY_THRESHOLD).then_some(DuplicateMatch { task, similarity })
+        })
+        .collect();
+
+    matches.sort_by(|a, b| {
+        b.similarity
+            .total_cmp(&a.similarity)
+            .then(a.task.id.cmp(&b.task.id))
+    });
+    Ok(matches)
+}
+
+/// Lowercases and collapses everything that isn't a letter or digit into
+/// single spaces.
+fn normalize(text: &str) -> String {
+    text.split(|ch: char| !ch.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Word trigrams in the style of Postgres `pg_trgm`: each word is padded with
+/// two leading spaces and one trailing space before slicing.
+fn trigrams(text: &str) -> HashSet<String> {
+    let mut set = HashSet::new();
+    for word in normalize(text).split(' ').filter(|word| !word.is_empty()) {
+        let padded: Vec<char> = format!("  {word} ").chars().collect();
+        for window in padded.windows(3) {
+            set.insert(window.iter().collect());
+        }
+    }
+    set
+}
+
+fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::conn;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn find_duplicates_matches_reworded_open_tasks() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let conn = conn::connect(&temp.path().join("pearls.db"))
+            .await
+            .expect("connect");
+
+        let parser = tasks::add_task(
+            &conn,
+            "Implement the config parser",
+            "Parse pearls.toml into Config",
+            None,
+            None,
+        )
+        .await
+        .expect("add parser");
+        let docs = tasks::add_task(&conn, "Write docs", "README usage", None, None)
+            .await
+            .expect("add docs");
+        let closed = tasks::add_task(&conn, "Write docs", "closed copy", None, None)
+            .await
+            .expect("add closed");
+        tasks::update_metadata(&conn, closed.id, None, None, None, Some(TaskState::Closed))
+            .await
+            .expect("close");
+
+        let found = find_duplicates(
+            &conn,
+            "implement config parser",
+            "parse pearls.toml into the Config struct",
+        )
+        .await
+        .expect("find reworded");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].task.id, parser.id);
+
+        let found = find_duplicates(&conn, "write  DOCS!", "something else entirely")
+            .await
+            .expect("find exact title");
+        let ids: Vec<i64> = found.iter().map(|found| found.task.id).collect();
+        assert_eq!(ids, vec![docs.id]);
+
+        let found = find_duplicates(&conn, "Add CI workflow", "GitHub actions")
+            .await
+            .expect("find none");
+        assert!(found.is_empty());
+    }
+}
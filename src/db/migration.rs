@@ -5,7 +5,18 @@ pub struct Migrator;
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20250203_000001_create_tables::Migration)]
+        vec![
+            Box::new(m20250203_000001_create_tables::Migration),
+            Box::new(m20261018_000001_create_sync_base::Migration),
+            Box::new(m20261018_000002_create_task_fts::Migration),
+            Box::new(m20261018_000003_add_task_key::Migration),
+            Box::new(m20261018_000004_add_task_timestamps::Migration),
+            Box::new(m20261018_000005_add_task_verify_and_notes::Migration),
+            Box::new(m20261018_000006_create_criteria::Migration),
+            Box::new(m20261018_000007_add_task_attempts::Migration),
+            Box::new(m20261019_000008_add_task_due_at::Migration),
+            Box::new(m20261019_000009_index_note_text::Migration),
+        ]
     }
 }
 
@@ -103,3 +114,633 @@ mod m20250203_000001_create_tables {
         ChildId,
     }
 }
+
+// `DeriveMigrationName` names a migration after its source file, so every
+// migration after the first declares its name explicitly.
+mod m20261018_000001_create_sync_base {
+    use sea_orm_migration::prelude::*;
+    use sea_query::{ColumnDef, Table};
+
+    pub struct Migration;
+
+    impl MigrationName for Migration {
+        fn name(&self) -> &str {
+            "m20261018_000001_create_sync_base"
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl MigrationTrait for Migration {
+        async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+            manager
+                .create_table(
+                    Table::create()
+                        .table(SyncBase::Table)
+                        .if_not_exists()
+                        .col(
+                            ColumnDef::new(SyncBase::TaskId)
+                                .integer()
+                                .not_null()
+                                .primary_key(),
+                        )
+                        .col(ColumnDef::new(SyncBase::Record).text().not_null())
+                        .to_owned(),
+                )
+                .await
+        }
+
+        async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+            manager
+                .drop_table(Table::drop().table(SyncBase::Table).to_owned())
+                .await
+        }
+    }
+
+    #[derive(DeriveIden)]
+    enum SyncBase {
+        Table,
+        TaskId,
+        Record,
+    }
+}
+
+mod m20261018_000002_create_task_fts {
+    use sea_orm_migration::prelude::*;
+
+    pub struct Migration;
+
+    impl MigrationName for Migration {
+        fn name(&self) -> &str {
+            "m20261018_000002_create_task_fts"
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl MigrationTrait for Migration {
+        async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+            // sea-query has no builder for virtual tables or triggers. The
+            // index keeps its own copy of the text, keyed by task id.
+            let db = manager.get_connection();
+            db.execute_unprepared(
+                "CREATE VIRTUAL TABLE IF NOT EXISTS task_fts \
+                 USING fts5(title, desc, tokenize = 'porter unicode61')",
+            )
+            .await?;
+            db.execute_unprepared(
+                "CREATE TRIGGER IF NOT EXISTS task_fts_insert AFTER INSERT ON task BEGIN \
+                 INSERT INTO task_fts(rowid, title, desc) VALUES (new.id, new.title, new.desc); \
+                 END",
+            )
+            .await?;
+            db.execute_unprepared(
+                "CREATE TRIGGER IF NOT EXISTS task_fts_update AFTER UPDATE OF title, desc ON task BEGIN \
+                 UPDATE task_fts SET title = new.title, desc = new.desc WHERE rowid = new.id; \
+                 END",
+            )
+            .await?;
+            db.execute_unprepared(
+                "CREATE TRIGGER IF NOT EXISTS task_fts_delete AFTER DELETE ON task BEGIN \
+                 DELETE FROM task_fts WHERE rowid = old.id; \
+                 END",
+            )
+            .await?;
+            db.execute_unprepared(
+                "INSERT INTO task_fts(rowid, title, desc) SELECT id, title, desc FROM task",
+            )
+            .await?;
+            Ok(())
+        }
+
+        async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+            let db = manager.get_connection();
+            for trigger in ["task_fts_insert", "task_fts_update", "task_fts_delete"] {
+                db.execute_unprepared(&format!("DROP TRIGGER IF EXISTS {trigger}"))
+                    .await?;
+            }
+            db.execute_unprepared("DROP TABLE IF EXISTS task_fts").await?;
+            Ok(())
+        }
+    }
+}
+
+mod m20261018_000003_add_task_key {
+    use sea_orm_migration::prelude::*;
+    use sea_query::{ColumnDef, Index, Table};
+
+    pub struct Migration;
+
+    impl MigrationName for Migration {
+        fn name(&self) -> &str {
+            "m20261018_000003_add_task_key"
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl MigrationTrait for Migration {
+        async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Task::Table)
+                        .add_column(ColumnDef::new(Task::Key).text())
+                        .to_owned(),
+                )
+                .await?;
+            // SQLite allows any number of NULLs in a unique index, so only
+            // tasks that were given a key are constrained.
+            manager
+                .create_index(
+                    Index::create()
+                        .name("idx_task_key")
+                        .table(Task::Table)
+                        .col(Task::Key)
+                        .unique()
+                        .if_not_exists()
+                        .to_owned(),
+                )
+                .await
+        }
+
+        async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+            manager
+                .drop_index(Index::drop().name("idx_task_key").to_owned())
+                .await?;
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Task::Table)
+                        .drop_column(Task::Key)
+                        .to_owned(),
+                )
+                .await
+        }
+    }
+
+    #[derive(DeriveIden)]
+    enum Task {
+        Table,
+        Key,
+    }
+}
+
+mod m20261018_000004_add_task_timestamps {
+    use sea_orm_migration::prelude::*;
+    use sea_query::{ColumnDef, Table};
+
+    pub struct Migration;
+
+    impl MigrationName for Migration {
+        fn name(&self) -> &str {
+            "m20261018_000004_add_task_timestamps"
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl MigrationTrait for Migration {
+        async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+            for column in [Task::CreatedAt, Task::UpdatedAt] {
+                manager
+                    .alter_table(
+                        Table::alter()
+                            .table(Task::Table)
+                            .add_column(ColumnDef::new(column).integer().not_null().default(0))
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+
+            // SQLite only accepts constant defaults on ALTER TABLE, so unix
+            // timestamps are filled in by triggers. Writers that supply their
+            // own (e.g. imports) are left alone.
+            let db = manager.get_connection();
+            db.execute_unprepared(
+                "UPDATE task SET created_at = unixepoch(), updated_at = unixepoch()",
+            )
+            .await?;
+            db.execute_unprepared(
+                "CREATE TRIGGER IF NOT EXISTS task_created AFTER INSERT ON task \
+                 WHEN new.created_at = 0 OR new.updated_at = 0 BEGIN \
+                 UPDATE task SET created_at = CASE WHEN new.created_at = 0 \
+                 THEN unixepoch() ELSE new.created_at END, \
+                 updated_at = CASE WHEN new.updated_at = 0 \
+                 THEN unixepoch() ELSE new.updated_at END \
+                 WHERE id = new.id; \
+                 END",
+            )
+            .await?;
+            db.execute_unprepared(
+                "CREATE TRIGGER IF NOT EXISTS task_updated AFTER UPDATE ON task \
+                 WHEN new.updated_at = old.updated_at BEGIN \
+                 UPDATE task SET updated_at = unixepoch() WHERE id = new.id; \
+                 END",
+            )
+            .await?;
+            Ok(())
+        }
+
+        async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+            let db = manager.get_connection();
+            for trigger in ["task_created", "task_updated"] {
+                db.execute_unprepared(&format!("DROP TRIGGER IF EXISTS {trigger}"))
+                    .await?;
+            }
+            for column in [Task::CreatedAt, Task::UpdatedAt] {
+                manager
+                    .alter_table(
+                        Table::alter()
+                            .table(Task::Table)
+                            .drop_column(column)
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+            Ok(())
+        }
+    }
+
+    #[derive(DeriveIden)]
+    enum Task {
+        Table,
+        CreatedAt,
+        UpdatedAt,
+    }
+}
+
+mod m20261018_000005_add_task_verify_and_notes {
+    use sea_orm_migration::prelude::*;
+    use sea_query::{ColumnDef, Index, Table};
+
+    pub struct Migration;
+
+    impl MigrationName for Migration {
+        fn name(&self) -> &str {
+            "m20261018_000005_add_task_verify_and_notes"
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl MigrationTrait for Migration {
+        async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Task::Table)
+                        .add_column(ColumnDef::new(Task::Verify).text())
+                        .to_owned(),
+                )
+                .await?;
+            manager
+                .create_table(
+                    Table::create()
+                        .table(Note::Table)
+                        .if_not_exists()
+                        .col(
+                            ColumnDef::new(Note::Id)
+                                .integer()
+                                .not_null()
+                                .primary_key(),
+                        )
+                        .col(ColumnDef::new(Note::TaskId).integer().not_null())
+                        .col(ColumnDef::new(Note::Body).text().not_null())
+                        .col(
+                            ColumnDef::new(Note::CreatedAt)
+                                .integer()
+                                .not_null()
+                                .default(0),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+            manager
+                .create_index(
+                    Index::create()
+                        .name("idx_note_task_id")
+                        .table(Note::Table)
+                        .col(Note::TaskId)
+                        .if_not_exists()
+                        .to_owned(),
+                )
+                .await
+        }
+
+        async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+            manager
+                .drop_table(Table::drop().table(Note::Table).to_owned())
+                .await?;
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Task::Table)
+                        .drop_column(Task::Verify)
+                        .to_owned(),
+                )
+                .await
+        }
+    }
+
+    #[derive(DeriveIden)]
+    enum Task {
+        Table,
+        Verify,
+    }
+
+    #[derive(DeriveIden)]
+    enum Note {
+        Table,
+        Id,
+        TaskId,
+        Body,
+        CreatedAt,
+    }
+}
+
+mod m20261018_000006_create_criteria {
+    use sea_orm_migration::prelude::*;
+    use sea_query::{ColumnDef, Index, Table};
+
+    pub struct Migration;
+
+    impl MigrationName for Migration {
+        fn name(&self) -> &str {
+            "m20261018_000006_create_criteria"
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl MigrationTrait for Migration {
+        async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+            manager
+                .create_table(
+                    Table::create()
+                        .table(Criterion::Table)
+                        .if_not_exists()
+                        .col(
+                            ColumnDef::new(Criterion::Id)
+                                .integer()
+                                .not_null()
+                                .primary_key(),
+                        )
+                        .col(ColumnDef::new(Criterion::TaskId).integer().not_null())
+                        .col(ColumnDef::new(Criterion::Text).text().not_null())
+                        .col(
+                            ColumnDef::new(Criterion::Checked)
+                                .boolean()
+                                .not_null()
+                                .default(false),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+            manager
+                .create_index(
+                    Index::create()
+                        .name("idx_criterion_task_id")
+                        .table(Criterion::Table)
+                        .col(Criterion::TaskId)
+                        .if_not_exists()
+                        .to_owned(),
+                )
+                .await
+        }
+
+        async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+            manager
+                .drop_table(Table::drop().table(Criterion::Table).to_owned())
+                .await
+        }
+    }
+
+    #[derive(DeriveIden)]
+    enum Criterion {
+        Table,
+        Id,
+        TaskId,
+        Text,
+        Checked,
+    }
+}
+
+mod m20261018_000007_add_task_attempts {
+    use sea_orm_migration::prelude::*;
+    use sea_query::{ColumnDef, Table};
+
+    pub struct Migration;
+
+    impl MigrationName for Migration {
+        fn name(&self) -> &str {
+            "m20261018_000007_add_task_attempts"
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl MigrationTrait for Migration {
+        async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Task::Table)
+                        .add_column(
+                            ColumnDef::new(Task::Attempts)
+                                .integer()
+                                .not_null()
+                                .default(0),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Task::Table)
+                        .add_column(ColumnDef::new(Task::NotBefore).integer())
+                        .to_owned(),
+                )
+                .await
+        }
+
+        async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+            for column in [Task::Attempts, Task::NotBefore] {
+                manager
+                    .alter_table(
+                        Table::alter()
+                            .table(Task::Table)
+                            .drop_column(column)
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+            Ok(())
+        }
+    }
+
+    #[derive(DeriveIden)]
+    enum Task {
+        Table,
+        Attempts,
+        NotBefore,
+    }
+}
+
+mod m20261019_000008_add_task_due_at {
+    use sea_orm_migration::prelude::*;
+    use sea_query::{ColumnDef, Table};
+
+    pub struct Migration;
+
+    impl MigrationName for Migration {
+        fn name(&self) -> &str {
+            "m20261019_000008_add_task_due_at"
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl MigrationTrait for Migration {
+        async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Task::Table)
+                        .add_column(ColumnDef::new(Task::DueAt).integer())
+                        .to_owned(),
+                )
+                .await
+        }
+
+        async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Task::Table)
+                        .drop_column(Task::DueAt)
+                        .to_owned(),
+                )
+                .await
+        }
+    }
+
+    #[derive(DeriveIden)]
+    enum Task {
+        Table,
+        DueAt,
+    }
+}
+
+mod m20261019_000009_index_note_text {
+    use sea_orm_migration::prelude::*;
+
+    pub struct Migration;
+
+    impl MigrationName for Migration {
+        fn name(&self) -> &str {
+            "m20261019_000009_index_note_text"
+        }
+    }
+
+    /// Every note body of a task, one per line, for the `notes` column.
+    const NOTES_OF: &str =
+        "(SELECT group_concat(body, char(10)) FROM (SELECT body FROM note WHERE task_id = {id} ORDER BY id))";
+
+    #[async_trait::async_trait]
+    impl MigrationTrait for Migration {
+        async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+            // FTS5 tables can't gain columns, so the index is rebuilt with a
+            // third column holding the task's notes.
+            let db = manager.get_connection();
+            for trigger in ["task_fts_insert", "task_fts_update", "task_fts_delete"] {
+                db.execute_unprepared(&format!("DROP TRIGGER IF EXISTS {trigger}"))
+                    .await?;
+            }
+            db.execute_unprepared("DROP TABLE IF EXISTS task_fts").await?;
+            db.execute_unprepared(
+                "CREATE VIRTUAL TABLE task_fts \
+                 USING fts5(title, desc, notes, tokenize = 'porter unicode61')",
+            )
+            .await?;
+            db.execute_unprepared(
+                "CREATE TRIGGER task_fts_insert AFTER INSERT ON task BEGIN \
+                 INSERT INTO task_fts(rowid, title, desc, notes) \
+                 VALUES (new.id, new.title, new.desc, ''); \
+                 END",
+            )
+            .await?;
+            db.execute_unprepared(
+                "CREATE TRIGGER task_fts_update AFTER UPDATE OF title, desc ON task BEGIN \
+                 UPDATE task_fts SET title = new.title, desc = new.desc WHERE rowid = new.id; \
+                 END",
+            )
+            .await?;
+            db.execute_unprepared(
+                "CREATE TRIGGER task_fts_delete AFTER DELETE ON task BEGIN \
+                 DELETE FROM task_fts WHERE rowid = old.id; \
+                 END",
+            )
+            .await?;
+            for (trigger, event, row) in [
+                ("note_fts_insert", "INSERT", "new"),
+                ("note_fts_update", "UPDATE OF body", "new"),
+                ("note_fts_delete", "DELETE", "old"),
+            ] {
+                let notes = NOTES_OF.replace("{id}", &format!("{row}.task_id"));
+                db.execute_unprepared(&format!(
+                    "CREATE TRIGGER {trigger} AFTER {event} ON note BEGIN \
+                     UPDATE task_fts SET notes = coalesce({notes}, '') \
+                     WHERE rowid = {row}.task_id; \
+                     END"
+                ))
+                .await?;
+            }
+            let notes = NOTES_OF.replace("{id}", "task.id");
+            db.execute_unprepared(&format!(
+                "INSERT INTO task_fts(rowid, title, desc, notes) \
+                 SELECT id, title, desc, coalesce({notes}, '') FROM task"
+            ))
+            .await?;
+            Ok(())
+        }
+
+        async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+            // Back to the title/desc index of m20261018_000002.
+            let db = manager.get_connection();
+            for trigger in [
+                "note_fts_insert",
+                "note_fts_update",
+                "note_fts_delete",
+                "task_fts_insert",
+                "task_fts_update",
+                "task_fts_delete",
+            ] {
+                db.execute_unprepared(&format!("DROP TRIGGER IF EXISTS {trigger}"))
+                    .await?;
+            }
+            db.execute_unprepared("DROP TABLE IF EXISTS task_fts").await?;
+            db.execute_unprepared(
+                "CREATE VIRTUAL TABLE task_fts \
+                 USING fts5(title, desc, tokenize = 'porter unicode61')",
+            )
+            .await?;
+            db.execute_unprepared(
+                "CREATE TRIGGER task_fts_insert AFTER INSERT ON task BEGIN \
+                 INSERT INTO task_fts(rowid, title, desc) VALUES (new.id, new.title, new.desc); \
+                 END",
+            )
+            .await?;
+            db.execute_unprepared(
+                "CREATE TRIGGER task_fts_update AFTER UPDATE OF title, desc ON task BEGIN \
+                 UPDATE task_fts SET title = new.title, desc = new.desc WHERE rowid = new.id; \
+                 END",
+            )
+            .await?;
+            db.execute_unprepared(
+                "CREATE TRIGGER task_fts_delete AFTER DELETE ON task BEGIN \
+                 DELETE FROM task_fts WHERE rowid = old.id; \
+                 END",
+            )
+            .await?;
+            db.execute_unprepared(
+                "INSERT INTO task_fts(rowid, title, desc) SELECT id, title, desc FROM task",
+            )
+            .await?;
+            Ok(())
+        }
+    }
+}
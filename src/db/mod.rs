@@ -1,3 +1,9 @@
 pub mod conn;
+pub mod criteria;
+pub mod dump;
+pub mod duplicates;
 pub mod migration;
+pub mod notes;
+pub mod sync;
 pub mod tasks;
+pub mod watch;
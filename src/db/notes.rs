@@ -0,0 +1,105 @@
+use sea_orm_migration::prelude::ConnectionTrait;
+use sea_orm_migration::sea_orm::{DatabaseConnection, DbBackend, DbErr, QueryResult, Statement};
+use sea_query::{Expr, Iden, InsertStatement, Order, Query, SqliteQueryBuilder};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+#[derive(Iden)]
+pub(crate) enum Note {
+    Table,
+    Id,
+    TaskId,
+    Body,
+    CreatedAt,
+}
+
+/// Free-form text attached to a task, such as the output of a failed verify.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct NoteRow {
+    pub id: i64,
+    pub task_id: i64,
+    pub body: String,
+    pub created_at: i64,
+}
+
+pub async fn add_note<C: ConnectionTrait>(
+    conn: &C,
+    task_id: i64,
+    body: &str,
+    created_at: Option<i64>,
+) -> Result<NoteRow, DbErr> {
+    let created_at = match created_at {
+        Some(created_at) => Expr::val(created_at).into(),
+        None => Expr::cust("unixepoch()"),
+    };
+    let mut insert = InsertStatement::new();
+    insert
+        .into_table(Note::Table)
+        .columns([Note::TaskId, Note::Body, Note::CreatedAt])
+        .values([
+            Expr::val(task_id).into(),
+            Expr::val(body).into(),
+            created_at,
+        ])
+        .map_err(|err| DbErr::Custom(err.to_string()))?
+        .returning(Query::returning().columns(note_columns()));
+
+    let (sql, values) = insert.build(SqliteQueryBuilder);
+    let row = conn
+        .query_one(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            sql,
+            values,
+        ))
+        .await?
+        .ok_or_else(|| DbErr::Custom("failed to read inserted note".to_string()))?;
+    note_row(&row)
+}
+
+/// Notes on `task_id`, oldest first.
+pub async fn list_notes(conn: &DatabaseConnection, task_id: i64) -> Result<Vec<NoteRow>, DbErr> {
+    let (sql, values) = Query::select()
+        .columns(note_columns())
+        .from(Note::Table)
+        .and_where(Expr::col(Note::TaskId).eq(task_id))
+        .order_by(Note::Id, Order::Asc)
+        .build(SqliteQueryBuilder);
+    let rows: Vec<QueryResult> = conn
+        .query_all(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            sql,
+            values,
+        ))
+        .await?;
+    rows.iter().map(note_row).collect()
+}
+
+/// Every note, for dumps.
+pub async fn all_notes(conn: &DatabaseConnection) -> Result<Vec<NoteRow>, DbErr> {
+    let (sql, values) = Query::select()
+        .columns(note_columns())
+        .from(Note::Table)
+        .order_by(Note::Id, Order::Asc)
+        .build(SqliteQueryBuilder);
+    let rows: Vec<QueryResult> = conn
+        .query_all(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            sql,
+            values,
+        ))
+        .await?;
+    rows.iter().map(note_row).collect()
+}
+
+fn note_columns() -> [Note; 4] {
+    [Note::Id, Note::TaskId, Note::Body, Note::CreatedAt]
+}
+
+fn note_row(row: &QueryResult) -> Result<NoteRow, DbErr> {
+    Ok(NoteRow {
+        id: row.try_get_by_index(0)?,
+        task_id: row.try_get_by_index(1)?,
+        body: row.try_get_by_index(2)?,
+        created_at: row.try_get_by_index(3)?,
+    })
+}
@@ -0,0 +1,407 @@
+use std::collections::{HashMap, HashSet};
+
+use sea_orm_migration::prelude::ConnectionTrait;
+use sea_orm_migration::sea_orm::{
+    DatabaseConnection, DbBackend, DbErr, QueryResult, Statement, TransactionTrait,
+};
+use sea_query::{Expr, Iden, InsertStatement, OnConflict, Query, SqliteQueryBuilder};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::TaskState;
+use crate::db::criteria::{self, CriterionRow};
+use crate::db::dump::{self, DumpTask};
+use crate::db::tasks::{Dependency, Task};
+
+#[derive(Iden)]
+enum SyncBase {
+    Table,
+    TaskId,
+    Record,
+}
+
+/// One line of the sync file. Dependencies are stored on the child as a
+/// sorted parent list so an edge change only touches a single line.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SyncRecord {
+    pub id: i64,
+    pub title: Option<String>,
+    pub desc: Option<String>,
+    pub priority: i64,
+    pub state: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verify: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub criteria: Vec<CriterionRow>,
+    #[serde(default)]
+    pub parents: Vec<i64>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SyncConflict {
+    pub id: i64,
+    pub local: SyncRecord,
+    pub incoming: SyncRecord,
+}
+
+#[derive(Debug, Default, Serialize, JsonSchema)]
+pub struct MergeSummary {
+    pub added: Vec<i64>,
+    pub updated: Vec<i64>,
+    pub kept_local: Vec<i64>,
+    pub unchanged: usize,
+    pub conflicts: Vec<SyncConflict>,
+}
+
+pub async fn load_records(conn: &DatabaseConnection) -> Result<Vec<SyncRecord>, DbErr> {
+    let dump = dump::export(conn).await?;
+    let mut parents_by_child: HashMap<i64, Vec<i64>> = HashMap::new();
+    for dep in dump.dependencies {
+        parents_by_child
+            .entry(dep.child_id)
+            .or_default()
+            .push(dep.parent_id);
+    }
+
+    let records = dump
+        .tasks
+        .into_iter()
+        .map(|task| {
+            let mut parents = parents_by_child.remove(&task.id).unwrap_or_default();
+            parents.sort_unstable();
+            SyncRecord {
+                id: task.id,
+                title: task.title,
+                desc: task.desc,
+                priority: task.priority,
+                state: task.state,
+                key: task.key,
+                verify: task.verify,
+                due_at: task.due_at,
+                criteria: task.criteria,
+                parents,
+            }
+        })
+        .collect();
+    Ok(records)
+}
+
+/// Renders records as sorted JSON lines, one task per line.
+pub fn render(records: &[SyncRecord]) -> Result<String, DbErr> {
+    let mut sorted: Vec<&SyncRecord> = records.iter().collect();
+    sorted.sort_by_key(|record| record.id);
+
+    let mut out = String::new();
+    for record in sorted {
+        out.push_str(&encode(record)?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+pub fn parse(contents: &str) -> Result<Vec<SyncRecord>, DbErr> {
+    let mut records = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: SyncRecord = serde_json::from_str(line)
+            .map_err(|err| DbErr::Custom(format!("line {}: {err}", index + 1)))?;
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Records the given records as the last synced version of each task, so the
+/// next import can tell local edits apart from edits made in the file.
+pub async fn mark_synced(conn: &DatabaseConnection, records: &[SyncRecord]) -> Result<(), DbErr> {
+    let txn = conn.begin().await?;
+    for record in records {
+        upsert_base(&txn, record).await?;
+    }
+    txn.commit().await
+}
+
+/// Three-way merge of file records into the database, using the stored sync
+/// base as the common ancestor. Tasks edited on only one side take that
+/// side's version; tasks edited on both sides are reported and left alone.
+pub async fn merge(
+    conn: &DatabaseConnection,
+    records: &[SyncRecord],
+) -> Result<MergeSummary, DbErr> {
+    let current: HashMap<i64, SyncRecord> = load_records(conn)
+        .await?
+        .into_iter()
+        .map(|record| (record.id, record))
+        .collect();
+    let bases = load_bases(conn).await?;
+
+    let mut known: HashSet<i64> = current.keys().copied().collect();
+    let mut seen = HashSet::new();
+    for record in records {
+        known.insert(record.id);
+        if !seen.insert(record.id) {
+            return Err(DbErr::Custom(format!(
+                "task {} appears more than once in sync file",
+                record.id
+            )));
+        }
+        match TaskState::parse(&record.state) {
+            None => {
+                return Err(DbErr::Custom(format!(
+                    "task {} has unknown state {:?}",
+                    record.id, record.state
+                )));
+            }
+            Some(state) if state.is_derived() => {
+                return Err(DbErr::Custom(format!(
+                    "task {} has state {:?}, which is derived from parents and not-before times and can't be synced",
+                    record.id, record.state
+                )));
+            }
+            Some(_) => {}
+        }
+    }
+    for record in records {
+        if let Some(missing) = record.parents.iter().find(|id| !known.contains(id)) {
+            return Err(DbErr::Custom(format!(
+                "task {} lists unknown parent {missing}",
+                record.id
+            )));
+        }
+    }
+
+    let mut summary = MergeSummary::default();
+    let mut to_insert = Vec::new();
+    let mut to_apply = Vec::new();
+    let mut synced = Vec::new();
+    for record in records {
+        let incoming = encode(record)?;
+        let Some(local) = current.get(&record.id) else {
+            summary.added.push(record.id);
+            to_insert.push(record);
+            to_apply.push(record);
+            synced.push(record);
+            continue;
+        };
+
+        let local_line = encode(local)?;
+        if local_line == incoming {
+            summary.unchanged += 1;
+            synced.push(record);
+            continue;
+        }
+
+        match bases.get(&record.id) {
+            Some(base) if *base == local_line => {
+                summary.updated.push(record.id);
+                to_apply.push(record);
+                synced.push(record);
+            }
+            Some(base) if *base == incoming => summary.kept_local.push(record.id),
+            _ => summary.conflicts.push(SyncConflict {
+                id: record.id,
+                local: local.clone(),
+                incoming: record.clone(),
+            }),
+        }
+    }
+
+    let txn = conn.begin().await?;
+    for record in &to_insert {
+        let task = DumpTask {
+            id: record.id,
+            title: record.title.clone(),
+            desc: record.desc.clone(),
+            priority: record.priority,
+            state: record.state.clone(),
+            key: record.key.clone(),
+            verify: record.verify.clone(),
+            attempts: 0,
+            not_before: None,
+            due_at: record.due_at,
+            criteria: record.criteria.clone(),
+            created_at: 0,
+            updated_at: 0,
+        };
+        dump::insert_task(&txn, &task, true).await?;
+    }
+    for record in &to_apply {
+        apply(&txn, record).await?;
+    }
+    for record in &synced {
+        upsert_base(&txn, record).await?;
+    }
+    txn.commit().await?;
+
+    Ok(summary)
+}
+
+async fn apply<C: ConnectionTrait>(conn: &C, record: &SyncRecord) -> Result<(), DbErr> {
+    let (sql, values) = Query::update()
+        .table(Task::Table)
+        .value(Task::Title, record.title.clone())
+        .value(Task::Desc, record.desc.clone())
+        .value(Task::Priority, record.priority)
+        .value(Task::State, record.state.as_str())
+        .value(Task::Key, record.key.clone())
+        .value(Task::Verify, record.verify.clone())
+        .value(Task::DueAt, record.due_at)
+        .and_where(Expr::col(Task::Id).eq(record.id))
+        .build(SqliteQueryBuilder);
+    conn.execute(Statement::from_sql_and_values(
+        DbBackend::Sqlite,
+        sql,
+        values,
+    ))
+    .await?;
+    criteria::replace_criteria(conn, record.id, &record.criteria).await?;
+
+    let (sql, values) = Query::delete()
+        .from_table(Dependency::Table)
+        .and_where(Expr::col(Dependency::ChildId).eq(record.id))
+        .build(SqliteQueryBuilder);
+    conn.execute(Statement::from_sql_and_values(
+        DbBackend::Sqlite,
+        sql,
+        values,
+    ))
+    .await?;
+
+    for parent in &record.parents {
+        let mut insert = InsertStatement::new();
+        insert
+            .into_table(Dependency::Table)
+            .columns([Dependency::ParentId, Dependency::ChildId])
+            .values([Expr::val(*parent).into(), Expr::val(record.id).into()])
+            .map_err(|err| DbErr::Custom(err.to_string()))?
+            .on_conflict(
+                OnConflict::columns([Dependency::ParentId, Dependency::ChildId])
+                    .do_nothing()
+                    .to_owned(),
+            );
+        let (sql, values) = insert.build(SqliteQueryBuilder);
+        conn.execute(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            sql,
+            values,
+        ))
+        .await?;
+    }
+
+    Ok(())
+}
+
+async fn load_bases(conn: &DatabaseConnection) -> Result<HashMap<i64, String>, DbErr> {
+    let (sql, values) = Query::select()
+        .columns([SyncBase::TaskId, SyncBase::Record])
+        .from(SyncBase::Table)
+        .build(SqliteQueryBuilder);
+    let rows: Vec<QueryResult> = conn
+        .query_all(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            sql,
+            values,
+        ))
+        .await?;
+
+    let mut map = HashMap::new();
+    for row in rows {
+        let id: i64 = row.try_get_by_index(0)?;
+        let record: String = row.try_get_by_index(1)?;
+        map.insert(id, record);
+    }
+    Ok(map)
+}
+
+async fn upsert_base<C: ConnectionTrait>(conn: &C, record: &SyncRecord) -> Result<(), DbErr> {
+    let mut insert = InsertStatement::new();
+    insert
+        .into_table(SyncBase::Table)
+        .columns([SyncBase::TaskId, SyncBase::Record])
+        .values([
+            Expr::val(record.id).into(),
+            Expr::val(encode(record)?).into(),
+        ])
+        .map_err(|err| DbErr::Custom(err.to_string()))?
+        .on_conflict(
+            OnConflict::column(SyncBase::TaskId)
+                .update_column(SyncBase::Record)
+                .to_owned(),
+        );
+    let (sql, values) = insert.build(SqliteQueryBuilder);
+    conn.execute(Statement::from_sql_and_values(
+        DbBackend::Sqlite,
+        sql,
+        values,
+    ))
+    .await?;
+    Ok(())
+}
+
+fn encode(record: &SyncRecord) -> Result<String, DbErr> {
+    serde_json::to_string(record).map_err(|err| DbErr::Custom(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{conn, tasks};
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn merge_applies_file_edits_and_reports_conflicts() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let conn = conn::connect(&temp.path().join("pearls.db"))
+            .await
+            .expect("connect");
+
+        let one = tasks::add_task(&conn, "one", "first", None, None)
+            .await
+            .expect("add one");
+        let two = tasks::add_task(&conn, "two", "second", None, None)
+            .await
+            .expect("add two");
+
+        let records = load_records(&conn).await.expect("load");
+        mark_synced(&conn, &records).await.expect("mark synced");
+
+        // File side: retitle one, edit two, add a new child of one.
+        let mut file = parse(&render(&records).expect("render")).expect("parse");
+        file[0].title = Some("one (file)".to_string());
+        file[1].priority = 5;
+        file.push(SyncRecord {
+            id: 10,
+            title: Some("new".to_string()),
+            desc: None,
+            priority: 1,
+            state: "ready".to_string(),
+            key: None,
+            verify: None,
+            due_at: None,
+            criteria: Vec::new(),
+            parents: vec![one.id],
+        });
+
+        // Local side: also edit two, concurrently.
+        tasks::update_metadata(&conn, two.id, Some("two (local)"), None, None, None)
+            .await
+            .expect("local edit");
+
+        let summary = merge(&conn, &file).await.expect("merge");
+        assert_eq!(summary.added, vec![10]);
+        assert_eq!(summary.updated, vec![one.id]);
+        assert_eq!(summary.conflicts.len(), 1);
+        assert_eq!(summary.conflicts[0].id, two.id);
+
+        let merged_one = tasks::get_task_by_id(&conn, one.id).await.expect("one");
+        assert_eq!(merged_one.title.as_deref(), Some("one (file)"));
+        assert_eq!(merged_one.children, vec![10]);
+        let kept_two = tasks::get_task_by_id(&conn, two.id).await.expect("two");
+        assert_eq!(kept_two.title.as_deref(), Some("two (local)"));
+        assert_eq!(kept_two.priority, 1);
+    }
+}
@@ -1,25 +1,44 @@
 use sea_orm_migration::prelude::ConnectionTrait;
 use sea_orm_migration::sea_orm::{
-    DatabaseConnection, DbBackend, DbErr, ExecResult, QueryResult, Statement,
+    DatabaseConnection, DbBackend, DbErr, ExecResult, QueryResult, Statement, TransactionTrait,
 };
-use sea_query::{Expr, Iden, InsertStatement, OnConflict, Query, SimpleExpr, SqliteQueryBuilder};
+use sea_query::{
+    Cond, Condition, Expr, Iden, InsertStatement, LikeExpr, OnConflict, Order, Query,
+    SelectStatement, SimpleExpr, SqliteQueryBuilder, Value,
+};
+use schemars::JsonSchema;
 use serde::Serialize;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
-use crate::cli::TaskState;
+use crate::cli::{Schedule, TaskRef, TaskSort, TaskState};
+use crate::db::criteria::{self, CriterionRow};
+use crate::db::notes;
 
 #[derive(Iden)]
-enum Task {
+pub(crate) enum Task {
     Table,
     Id,
     Title,
     Desc,
     Priority,
     State,
+    Key,
+    CreatedAt,
+    UpdatedAt,
+    Verify,
+    Attempts,
+    NotBefore,
+    DueAt,
 }
 
 #[derive(Iden)]
-enum Dependency {
+enum TaskFts {
+    Table,
+    Rowid,
+}
+
+#[derive(Iden)]
+pub(crate) enum Dependency {
     Table,
     ParentId,
     ChildId,
@@ -30,6 +49,7 @@ pub async fn add_task(
     title: &str,
     description: &str,
     priority: Option<i64>,
+    key: Option<&str>,
 ) -> Result<TaskRow, DbErr> {
     let mut insert = InsertStatement::new();
     let mut columns: Vec<Task> = vec![Task::Title, Task::Desc];
@@ -38,6 +58,10 @@ pub async fn add_task(
         columns.push(Task::Priority);
         values.push(Expr::val(priority).into());
     }
+    if let Some(key) = key {
+        columns.push(Task::Key);
+        values.push(Expr::val(key).into());
+    }
     insert
         .into_table(Task::Table)
         .columns(columns)
@@ -69,13 +93,7 @@ pub async fn add_task(
 
 pub async fn get_task_by_id(conn: &DatabaseConnection, id: i64) -> Result<TaskRow, DbErr> {
     let (sql, values) = Query::select()
-        .columns([
-            (Task::Table, Task::Id),
-            (Task::Table, Task::Title),
-            (Task::Table, Task::Desc),
-            (Task::Table, Task::Priority),
-            (Task::Table, Task::State),
-        ])
+        .columns(task_columns())
         .from(Task::Table)
         .and_where(Expr::col((Task::Table, Task::Id)).eq(id))
         .build(SqliteQueryBuilder);
@@ -89,16 +107,8 @@ pub async fn get_task_by_id(conn: &DatabaseConnection, id: i64) -> Result<TaskRo
         .await?
         .ok_or_else(|| DbErr::Custom(format!("task {id} not found")))?;
 
-    let id: i64 = row.try_get_by_index(0)?;
-    let mut task = TaskRow {
-        id,
-        title: row.try_get_by_index(1)?,
-        desc: row.try_get_by_index(2)?,
-        priority: row.try_get_by_index(3)?,
-        state: row.try_get_by_index(4)?,
-        parents: Vec::new(),
-        children: Vec::new(),
-    };
+    let mut task = task_row(&row)?;
+    let id = task.id;
 
     populate_dependencies(
         conn,
@@ -109,19 +119,61 @@ pub async fn get_task_by_id(conn: &DatabaseConnection, id: i64) -> Result<TaskRo
     Ok(task)
 }
 
+pub async fn get_task_by_key(
+    conn: &DatabaseConnection,
+    key: &str,
+) -> Result<Option<TaskRow>, DbErr> {
+    match resolve_key(conn, key).await? {
+        Some(id) => get_task_by_id(conn, id).await.map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Turns a command-line task reference into a task id. Numeric ids are
+/// passed through unchecked; keys must belong to an existing task.
+pub async fn resolve_ref(conn: &DatabaseConnection, task: &TaskRef) -> Result<i64, DbErr> {
+    match task {
+        TaskRef::Id(id) => Ok(*id),
+        TaskRef::Key(key) => resolve_key(conn, key)
+            .await?
+            .ok_or_else(|| DbErr::Custom(format!("no task with key {key:?}"))),
+    }
+}
+
+pub async fn resolve_refs(
+    conn: &DatabaseConnection,
+    tasks: &[TaskRef],
+) -> Result<Vec<i64>, DbErr> {
+    let mut ids = Vec::with_capacity(tasks.len());
+    for task in tasks {
+        ids.push(resolve_ref(conn, task).await?);
+    }
+    Ok(ids)
+}
+
+async fn resolve_key(conn: &DatabaseConnection, key: &str) -> Result<Option<i64>, DbErr> {
+    let (sql, values) = Query::select()
+        .column(Task::Id)
+        .from(Task::Table)
+        .and_where(Expr::col(Task::Key).eq(key))
+        .build(SqliteQueryBuilder);
+    let row = conn
+        .query_one(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            sql,
+            values,
+        ))
+        .await?;
+    row.map(|row| row.try_get_by_index(0)).transpose()
+}
+
 pub async fn list_tasks(
     conn: &DatabaseConnection,
     states: &[TaskState],
 ) -> Result<Vec<TaskRow>, DbErr> {
     let mut query = Query::select();
     query
-        .columns([
-            (Task::Table, Task::Id),
-            (Task::Table, Task::Title),
-            (Task::Table, Task::Desc),
-            (Task::Table, Task::Priority),
-            (Task::Table, Task::State),
-        ])
+        .columns(task_columns())
         .from(Task::Table);
 
     let (sql, values) = query.build(SqliteQueryBuilder);
@@ -135,15 +187,7 @@ pub async fn list_tasks(
 
     let mut tasks = Vec::with_capacity(rows.len());
     for row in rows {
-        tasks.push(TaskRow {
-            id: row.try_get_by_index(0)?,
-            title: row.try_get_by_index(1)?,
-            desc: row.try_get_by_index(2)?,
-            priority: row.try_get_by_index(3)?,
-            state: row.try_get_by_index(4)?,
-            parents: Vec::new(),
-            children: Vec::new(),
-        });
+        tasks.push(task_row(&row)?);
     }
 
     let ids: Vec<i64> = tasks.iter().map(|task| task.id).collect();
@@ -162,34 +206,394 @@ pub async fn list_tasks(
     Ok(filtered)
 }
 
+/// Filters shared by paginated listing and per-state counting. Task ids are
+/// already resolved; `states` match the derived state (so `blocked` includes
+/// tasks waiting on an open parent).
+#[derive(Debug, Default)]
+pub struct TaskFilter {
+    pub states: Vec<TaskState>,
+    pub priority_min: Option<i64>,
+    pub priority_max: Option<i64>,
+    pub parent: Option<i64>,
+    pub descendant_of: Option<i64>,
+    pub has_children: bool,
+    pub title_contains: Vec<String>,
+    /// Only open tasks whose due date has passed.
+    pub overdue: bool,
+}
+
+/// Ordering and paging for [`list_tasks_paginated`]. `cursor` continues
+/// after the last row of a previous page and must come from a page with the
+/// same sort and direction.
+#[derive(Debug)]
+pub struct PageRequest {
+    pub sort: TaskSort,
+    pub descending: bool,
+    pub offset: u64,
+    pub limit: u64,
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TaskPage {
+    pub items: Vec<TaskRow>,
+    /// Number of tasks matching the filter across all pages.
+    pub total: u64,
+    pub next_cursor: Option<String>,
+}
+
 pub async fn list_tasks_paginated(
     conn: &DatabaseConnection,
-    states: &[TaskState],
-    offset: u64,
-    limit: u64,
-) -> Result<Vec<TaskRow>, DbErr> {
+    filter: &TaskFilter,
+    page: &PageRequest,
+) -> Result<TaskPage, DbErr> {
+    let (tasks, next_cursor) = fetch_page(conn, filter, page).await?;
+
+    let mut count = Query::select();
+    count.expr(Expr::cust("COUNT(*)")).from(Task::Table);
+    apply_filter(&mut count, filter);
+    let (sql, values) = count.build(SqliteQueryBuilder);
+    let total: i64 = conn
+        .query_one(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            sql,
+            values,
+        ))
+        .await?
+        .ok_or_else(|| DbErr::Custom("failed to count tasks".to_string()))?
+        .try_get_by_index(0)?;
+
+    Ok(TaskPage {
+        items: tasks,
+        total: total as u64,
+        next_cursor,
+    })
+}
+
+/// Rows fetched per query when streaming with [`for_each_task`].
+const STREAM_CHUNK: u64 = 500;
+
+/// Walks every task matching `filter` in page order, fetching
+/// [`STREAM_CHUNK`] rows at a time so memory stays flat for large lists.
+/// `page.limit` caps the total number of tasks visited (`u64::MAX` for no
+/// cap). Stops early when `visit` returns `false`.
+pub async fn for_each_task<F>(
+    conn: &DatabaseConnection,
+    filter: &TaskFilter,
+    page: &PageRequest,
+    mut visit: F,
+) -> Result<(), DbErr>
+where
+    F: FnMut(TaskRow) -> bool,
+{
+    let mut remaining = page.limit;
+    let mut chunk = PageRequest {
+        sort: page.sort,
+        descending: page.descending,
+        offset: page.offset,
+        limit: remaining.min(STREAM_CHUNK),
+        cursor: page.cursor.clone(),
+    };
+    while remaining > 0 {
+        let (tasks, next_cursor) = fetch_page(conn, filter, &chunk).await?;
+        remaining -= tasks.len() as u64;
+        for task in tasks {
+            if !visit(task) {
+                return Ok(());
+            }
+        }
+        let Some(next_cursor) = next_cursor else {
+            break;
+        };
+        chunk.offset = 0;
+        chunk.limit = remaining.min(STREAM_CHUNK);
+        chunk.cursor = Some(next_cursor);
+    }
+    Ok(())
+}
+
+/// One page of tasks plus the cursor for the page after it, if any.
+async fn fetch_page(
+    conn: &DatabaseConnection,
+    filter: &TaskFilter,
+    page: &PageRequest,
+) -> Result<(Vec<TaskRow>, Option<String>), DbErr> {
+    let order = if page.descending {
+        Order::Desc
+    } else {
+        Order::Asc
+    };
+    // Fetch one extra row to learn whether another page exists. SQLite
+    // binds limits as signed integers, so clamp huge values.
+    let max = i64::MAX as u64;
+    let mut query = Query::select();
+    query
+        .columns(task_columns())
+        .from(Task::Table)
+        .offset(page.offset.min(max))
+        .limit(page.limit.saturating_add(1).min(max));
+    apply_filter(&mut query, filter);
+    if let Some(cursor) = &page.cursor {
+        let cursor = Cursor::decode(cursor)?;
+        if cursor.sort != page.sort.as_str() || cursor.descending != page.descending {
+            return Err(DbErr::Custom(
+                "cursor was issued for a different --sort/--desc".to_string(),
+            ));
+        }
+        query.cond_where(cursor.after(page.sort.column()));
+    }
+    if !matches!(page.sort, TaskSort::Id) {
+        query.order_by((Task::Table, page.sort.column()), order.clone());
+    }
+    query.order_by((Task::Table, Task::Id), order);
+
+    let (sql, values) = query.build(SqliteQueryBuilder);
+    let rows: Vec<QueryResult> = conn
+        .query_all(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            sql,
+            values,
+        ))
+        .await?;
+
+    let mut tasks = Vec::with_capacity(rows.len());
+    for row in rows {
+        tasks.push(task_row(&row)?);
+    }
+    let has_more = tasks.len() as u64 > page.limit;
+    tasks.truncate(page.limit as usize);
+    let next_cursor = tasks
+        .last()
+        .filter(|_| has_more)
+        .map(|last| Cursor::after_task(page, last).encode());
+
+    let ids: Vec<i64> = tasks.iter().map(|task| task.id).collect();
+    populate_dependencies(conn, &ids, &mut tasks).await?;
+    Ok((tasks, next_cursor))
+}
+
+/// Keyset position after a row: the sort value and id of the last task on a
+/// page. Encoded as `sort:asc|desc:value:id` so a stale cursor can be
+/// rejected instead of silently skipping rows.
+struct Cursor {
+    sort: String,
+    descending: bool,
+    value: i64,
+    id: i64,
+}
+
+impl Cursor {
+    fn after_task(page: &PageRequest, task: &TaskRow) -> Cursor {
+        let value = match page.sort {
+            TaskSort::Priority => task.priority,
+            TaskSort::Id => task.id,
+            TaskSort::Created => task.created_at,
+            TaskSort::Updated => task.updated_at,
+        };
+        Cursor {
+            sort: page.sort.as_str().to_string(),
+            descending: page.descending,
+            value,
+            id: task.id,
+        }
+    }
+
+    fn encode(&self) -> String {
+        let direction = if self.descending { "desc" } else { "asc" };
+        format!("{}:{direction}:{}:{}", self.sort, self.value, self.id)
+    }
+
+    fn decode(text: &str) -> Result<Cursor, DbErr> {
+        let invalid = || DbErr::Custom(format!("invalid cursor {text:?}"));
+        let mut parts = text.split(':');
+        let (Some(sort), Some(direction), Some(value), Some(id), None) = (
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+        ) else {
+            return Err(invalid());
+        };
+        let descending = match direction {
+            "asc" => false,
+            "desc" => true,
+            _ => return Err(invalid()),
+        };
+        Ok(Cursor {
+            sort: sort.to_string(),
+            descending,
+            value: value.parse().map_err(|_| invalid())?,
+            id: id.parse().map_err(|_| invalid())?,
+        })
+    }
+
+    fn after(&self, sort_col: Task) -> Condition {
+        let id = Expr::col((Task::Table, Task::Id));
+        let value = Expr::col((Task::Table, sort_col));
+        if self.descending {
+            Cond::any()
+                .add(value.clone().lt(self.value))
+                .add(value.eq(self.value).and(id.lt(self.id)))
+        } else {
+            Cond::any()
+                .add(value.clone().gt(self.value))
+                .add(value.eq(self.value).and(id.gt(self.id)))
+        }
+    }
+}
+
+/// Counts tasks matching `filter`, grouped by derived state. Every state the
+/// filter allows is present, even when its count is zero.
+pub async fn count_tasks_by_state(
+    conn: &DatabaseConnection,
+    filter: &TaskFilter,
+) -> Result<BTreeMap<String, u64>, DbErr> {
     let mut query = Query::select();
     query
-        .columns([
-            (Task::Table, Task::Id),
-            (Task::Table, Task::Title),
-            (Task::Table, Task::Desc),
-            (Task::Table, Task::Priority),
-            (Task::Table, Task::State),
-        ])
+        .expr(effective_state())
+        .expr(Expr::cust("COUNT(*)"))
         .from(Task::Table)
-        .offset(offset)
-        .limit(limit);
+        .add_group_by([effective_state()]);
+    apply_filter(&mut query, filter);
 
-    if !states.is_empty() {
-        let allowed: Vec<SimpleExpr> = states
+    let (sql, values) = query.build(SqliteQueryBuilder);
+    let rows: Vec<QueryResult> = conn
+        .query_all(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            sql,
+            values,
+        ))
+        .await?;
+
+    let mut counts: BTreeMap<String, u64> = if filter.states.is_empty() {
+        TaskState::ALL
+            .iter()
+            .map(|state| (state.as_str().to_string(), 0))
+            .collect()
+    } else {
+        filter
+            .states
+            .iter()
+            .map(|state| (state.as_str().to_string(), 0))
+            .collect()
+    };
+    for row in rows {
+        let state: String = row.try_get_by_index(0)?;
+        let count: i64 = row.try_get_by_index(1)?;
+        counts.insert(state, count as u64);
+    }
+    Ok(counts)
+}
+
+fn apply_filter(query: &mut SelectStatement, filter: &TaskFilter) {
+    if !filter.states.is_empty() {
+        let allowed: Vec<SimpleExpr> = filter
+            .states
             .iter()
             .map(|state| Expr::val(state.as_str()).into())
             .collect();
-        query.and_where(Expr::col((Task::Table, Task::State)).is_in(allowed));
+        query.and_where(Expr::expr(effective_state()).is_in(allowed));
+    }
+    if let Some(min) = filter.priority_min {
+        query.and_where(Expr::col((Task::Table, Task::Priority)).gte(min));
+    }
+    if let Some(max) = filter.priority_max {
+        query.and_where(Expr::col((Task::Table, Task::Priority)).lte(max));
+    }
+    if filter.overdue {
+        query.and_where(Expr::cust(
+            "task.state <> 'closed' AND task.due_at < unixepoch()",
+        ));
     }
+    if let Some(parent) = filter.parent {
+        query.and_where(
+            Expr::col((Task::Table, Task::Id)).in_subquery(
+                Query::select()
+                    .column(Dependency::ChildId)
+                    .from(Dependency::Table)
+                    .and_where(Expr::col(Dependency::ParentId).eq(parent))
+                    .to_owned(),
+            ),
+        );
+    }
+    if let Some(ancestor) = filter.descendant_of {
+        // UNION (not UNION ALL) stops the walk if the graph has a cycle.
+        query.and_where(Expr::cust_with_values(
+            "task.id IN (WITH RECURSIVE descendant(id) AS ( \
+             SELECT child_id FROM dependency WHERE parent_id = ? \
+             UNION SELECT dependency.child_id FROM dependency \
+             JOIN descendant ON dependency.parent_id = descendant.id) \
+             SELECT id FROM descendant)",
+            [ancestor],
+        ));
+    }
+    if filter.has_children {
+        query.and_where(
+            Expr::col((Task::Table, Task::Id)).in_subquery(
+                Query::select()
+                    .column(Dependency::ParentId)
+                    .from(Dependency::Table)
+                    .to_owned(),
+            ),
+        );
+    }
+    for text in &filter.title_contains {
+        let escaped = text
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        query.and_where(
+            Expr::col((Task::Table, Task::Title))
+                .like(LikeExpr::new(format!("%{escaped}%")).escape('\\')),
+        );
+    }
+}
 
-    let (sql, values) = query.build(SqliteQueryBuilder);
+/// SQL for the state `populate_dependencies` reports: open tasks with any
+/// open parent are `blocked`, ready tasks whose start time hasn't come are
+/// `deferred`, everything else keeps its stored state.
+fn effective_state() -> SimpleExpr {
+    Expr::cust(
+        "CASE WHEN task.state <> 'closed' AND EXISTS ( \
+         SELECT 1 FROM dependency JOIN task AS parent ON parent.id = dependency.parent_id \
+         WHERE dependency.child_id = task.id AND parent.state <> 'closed') \
+         THEN 'blocked' \
+         WHEN task.state = 'ready' AND task.not_before > unixepoch() THEN 'deferred' \
+         ELSE task.state END",
+    )
+}
+
+/// Full-text search over task titles, descriptions and notes, best matches
+/// first.
+/// Each whitespace-separated term must match (as a word prefix); the snippet
+/// wraps matched terms in `highlight`.
+pub async fn search_tasks(
+    conn: &DatabaseConnection,
+    text: &str,
+    states: &[TaskState],
+    limit: u64,
+    highlight: (&str, &str),
+) -> Result<Vec<SearchHit>, DbErr> {
+    let Some(match_expr) = fts_match_expr(text) else {
+        return Ok(Vec::new());
+    };
+
+    let (sql, values) = Query::select()
+        .columns(task_columns())
+        .expr(Expr::cust_with_values(
+            "snippet(task_fts, -1, ?, ?, '...', 12)",
+            [highlight.0, highlight.1],
+        ))
+        .from(TaskFts::Table)
+        .inner_join(
+            Task::Table,
+            Expr::col((Task::Table, Task::Id)).equals((TaskFts::Table, TaskFts::Rowid)),
+        )
+        .and_where(Expr::cust_with_values("task_fts MATCH ?", [match_expr]))
+        .order_by_expr(Expr::cust("bm25(task_fts, 4.0, 1.0, 1.0)"), Order::Asc)
+        .order_by((Task::Table, Task::Id), Order::Asc)
+        .build(SqliteQueryBuilder);
     let rows: Vec<QueryResult> = conn
         .query_all(Statement::from_sql_and_values(
             DbBackend::Sqlite,
@@ -199,40 +603,202 @@ pub async fn list_tasks_paginated(
         .await?;
 
     let mut tasks = Vec::with_capacity(rows.len());
+    let mut snippets = Vec::with_capacity(rows.len());
     for row in rows {
-        tasks.push(TaskRow {
-            id: row.try_get_by_index(0)?,
-            title: row.try_get_by_index(1)?,
-            desc: row.try_get_by_index(2)?,
-            priority: row.try_get_by_index(3)?,
-            state: row.try_get_by_index(4)?,
-            parents: Vec::new(),
-            children: Vec::new(),
-        });
+        tasks.push(task_row(&row)?);
+        snippets.push(row.try_get_by_index::<String>(task_columns().len())?);
     }
 
+    // Filter after deriving `blocked`, as `list_tasks` does.
     let ids: Vec<i64> = tasks.iter().map(|task| task.id).collect();
     populate_dependencies(conn, &ids, &mut tasks).await?;
-    Ok(tasks)
+
+    let allowed: HashSet<&'static str> = states.iter().map(TaskState::as_str).collect();
+    let hits = tasks
+        .into_iter()
+        .zip(snippets)
+        .filter(|(task, _)| allowed.is_empty() || allowed.contains(task.state.as_str()))
+        .take(limit as usize)
+        .map(|(task, snippet)| SearchHit { task, snippet })
+        .collect();
+    Ok(hits)
+}
+
+/// Quotes every term so punctuation in user input can't be parsed as FTS5
+/// query syntax.
+fn fts_match_expr(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split_whitespace()
+        .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+        .collect();
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
 }
 
-pub async fn claim_next(conn: &DatabaseConnection) -> Result<Option<TaskRow>, DbErr> {
+/// Tasks due within this many seconds count as urgent under
+/// [`Schedule::Deadline`].
+const DUE_SOON: i64 = 24 * 60 * 60;
+
+pub async fn claim_next(
+    conn: &DatabaseConnection,
+    schedule: Schedule,
+) -> Result<Option<TaskRow>, DbErr> {
     let mut ready = list_tasks(conn, &[TaskState::Ready]).await?;
     if ready.is_empty() {
         return Ok(None);
     }
 
-    ready.sort_by_key(|task| (task.priority, task.id));
+    match schedule {
+        Schedule::Priority => ready.sort_by_key(|task| (task.priority, task.id)),
+        Schedule::Deadline => {
+            let now = unix_now();
+            ready.sort_by_key(|task| {
+                let urgency = match task.due_at {
+                    Some(due_at) if due_at < now => 0,
+                    Some(due_at) if due_at < now + DUE_SOON => 1,
+                    _ => 2,
+                };
+                (
+                    urgency,
+                    task.priority,
+                    task.due_at.unwrap_or(i64::MAX),
+                    task.id,
+                )
+            });
+        }
+        Schedule::Inherited => {
+            let ids: Vec<i64> = ready.iter().map(|task| task.id).collect();
+            let effective = effective_priorities(conn, &ids).await?;
+            ready.sort_by_key(|task| {
+                let inherited = effective.get(&task.id).copied();
+                (inherited.unwrap_or(task.priority), task.priority, task.id)
+            });
+        }
+    }
     let next_id = ready[0].id;
 
     update_metadata(conn, next_id, None, None, None, Some(TaskState::InProgress)).await?;
-    let updated = get_task_by_id(conn, next_id).await?;
+    let updated = get_task_detail(conn, next_id).await?;
     Ok(Some(updated))
 }
 
-pub async fn update_metadata(
+/// [`get_task_by_id`] plus the task's effective priority.
+pub async fn get_task_detail(conn: &DatabaseConnection, id: i64) -> Result<TaskRow, DbErr> {
+    let mut task = get_task_by_id(conn, id).await?;
+    task.effective_priority = effective_priorities(conn, &[id]).await?.remove(&id);
+    Ok(task)
+}
+
+/// The lowest priority number among each task and the open tasks that
+/// transitively depend on it, so work blocking something important is
+/// treated as important too.
+pub async fn effective_priorities(
+    conn: &DatabaseConnection,
+    ids: &[i64],
+) -> Result<HashMap<i64, i64>, DbErr> {
+    if ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let placeholders = vec!["?"; ids.len()].join(", ");
+    // UNION (not UNION ALL) stops the walk if the graph has a cycle. A
+    // closed task no longer waits on anything, so the walk stops there too.
+    let sql = format!(
+        "WITH RECURSIVE reach(root, id) AS ( \
+         SELECT id, id FROM task WHERE id IN ({placeholders}) \
+         UNION SELECT reach.root, dependency.child_id FROM reach \
+         JOIN dependency ON dependency.parent_id = reach.id \
+         JOIN task AS child ON child.id = dependency.child_id AND child.state <> 'closed') \
+         SELECT reach.root, MIN(task.priority) FROM reach JOIN task ON task.id = reach.id \
+         GROUP BY reach.root"
+    );
+    let rows: Vec<QueryResult> = conn
+        .query_all(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            sql,
+            ids.iter().map(|id| Value::from(*id)),
+        ))
+        .await?;
+    rows.iter()
+        .map(|row| Ok((row.try_get_by_index(0)?, row.try_get_by_index(1)?)))
+        .collect()
+}
+
+/// Earliest `not_before` among deferred tasks: when a task that
+/// [`claim_next`] currently skips becomes claimable.
+pub async fn next_claimable_at(conn: &DatabaseConnection) -> Result<Option<i64>, DbErr> {
+    let deferred = list_tasks(conn, &[TaskState::Deferred]).await?;
+    Ok(deferred.iter().filter_map(|task| task.not_before).min())
+}
+
+/// What [`record_failure`] did with the task.
+#[derive(Debug, PartialEq)]
+pub enum FailureOutcome {
+    /// Back in the queue, claimable from `not_before` on (if set).
+    Requeued,
+    /// Out of attempts; parked as `needs_human`.
+    NeedsHuman,
+}
+
+/// Records a failed attempt on an in-progress task: bumps `attempts`, keeps
+/// `reason` as a note, and either puts the task back in the queue (after
+/// `backoff`, doubled for every earlier failure) or parks it as
+/// `needs_human` once `max_attempts` is reached.
+pub async fn record_failure(
     conn: &DatabaseConnection,
     id: i64,
+    reason: &str,
+    max_attempts: i64,
+    backoff: Option<std::time::Duration>,
+) -> Result<FailureOutcome, DbErr> {
+    let task = get_task_by_id(conn, id).await?;
+    if task.state != "in_progress" {
+        return Err(DbErr::Custom(format!(
+            "task {id} is {}, not in_progress",
+            task.state
+        )));
+    }
+
+    let attempts = task.attempts + 1;
+    let (outcome, state, not_before) = if attempts >= max_attempts {
+        (FailureOutcome::NeedsHuman, TaskState::NeedsHuman, None)
+    } else {
+        let delay = backoff.map(|backoff| {
+            let doublings = u32::try_from(attempts - 1).unwrap_or(u32::MAX).min(16);
+            let seconds = backoff.saturating_mul(1 << doublings).as_secs();
+            i64::try_from(seconds).unwrap_or(i64::MAX)
+        });
+        let not_before = delay
+            .filter(|delay| *delay > 0)
+            .map(|delay| unix_now().saturating_add(delay));
+        (FailureOutcome::Requeued, TaskState::Ready, not_before)
+    };
+
+    let txn = conn.begin().await?;
+    let (sql, values) = Query::update()
+        .table(Task::Table)
+        .value(Task::State, state.as_str())
+        .value(Task::Attempts, attempts)
+        .value(Task::NotBefore, not_before)
+        .and_where(Expr::col(Task::Id).eq(id))
+        .build(SqliteQueryBuilder);
+    txn.execute(Statement::from_sql_and_values(
+        DbBackend::Sqlite,
+        sql,
+        values,
+    ))
+    .await?;
+    let note = format!("attempt {attempts} of {max_attempts} failed: {reason}");
+    notes::add_note(&txn, id, &note, None).await?;
+    txn.commit().await?;
+    Ok(outcome)
+}
+
+pub async fn update_metadata<C: ConnectionTrait>(
+    conn: &C,
+    id: i64,
     title: Option<&str>,
     desc: Option<&str>,
     priority: Option<i64>,
@@ -257,7 +823,19 @@ pub async fn update_metadata(
         changes += 1;
     }
     if let Some(state) = state {
+        if state.is_derived() {
+            return Err(DbErr::Custom(format!(
+                "`{}` is derived from parents and not-before times and can't be set",
+                state.as_str()
+            )));
+        }
         update.value(Task::State, state.as_str());
+        // Handing a task back from `needs_human` gives it a fresh set of
+        // attempts.
+        update.value(
+            Task::Attempts,
+            Expr::cust("CASE WHEN state = 'needs_human' THEN 0 ELSE attempts END"),
+        );
         changes += 1;
     }
 
@@ -276,6 +854,70 @@ pub async fn update_metadata(
     Ok(result.rows_affected())
 }
 
+/// Sets or (with `None`) removes the task's due date.
+pub async fn set_due_at(
+    conn: &DatabaseConnection,
+    id: i64,
+    due_at: Option<i64>,
+) -> Result<u64, DbErr> {
+    let (sql, values) = Query::update()
+        .table(Task::Table)
+        .value(Task::DueAt, due_at)
+        .and_where(Expr::col(Task::Id).eq(id))
+        .build(SqliteQueryBuilder);
+    let result: ExecResult = conn
+        .execute(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            sql,
+            values,
+        ))
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Sets or (with `None`) removes the time before which the task is
+/// `deferred` rather than `ready`.
+pub async fn set_not_before(
+    conn: &DatabaseConnection,
+    id: i64,
+    not_before: Option<i64>,
+) -> Result<u64, DbErr> {
+    let (sql, values) = Query::update()
+        .table(Task::Table)
+        .value(Task::NotBefore, not_before)
+        .and_where(Expr::col(Task::Id).eq(id))
+        .build(SqliteQueryBuilder);
+    let result: ExecResult = conn
+        .execute(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            sql,
+            values,
+        ))
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Sets or (with `None`) removes the command `tasks close` runs first.
+pub async fn set_verify(
+    conn: &DatabaseConnection,
+    id: i64,
+    verify: Option<&str>,
+) -> Result<u64, DbErr> {
+    let (sql, values) = Query::update()
+        .table(Task::Table)
+        .value(Task::Verify, verify)
+        .and_where(Expr::col(Task::Id).eq(id))
+        .build(SqliteQueryBuilder);
+    let result: ExecResult = conn
+        .execute(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            sql,
+            values,
+        ))
+        .await?;
+    Ok(result.rows_affected())
+}
+
 pub async fn add_dependency(
     conn: &DatabaseConnection,
     parent_id: i64,
@@ -347,15 +989,48 @@ async fn delete_dependency(
     Ok(())
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 pub struct TaskRow {
     pub id: i64,
     pub title: Option<String>,
     pub desc: Option<String>,
     pub priority: i64,
+    /// Derived state: `blocked` while any parent is not closed, `deferred`
+    /// while a ready task's `not_before` is still in the future.
+    #[schemars(extend(
+        "enum" = ["ready", "blocked", "deferred", "in_progress", "needs_human", "closed"]
+    ))]
     pub state: String,
+    pub key: Option<String>,
+    /// Shell command that must succeed before `tasks close` closes the task.
+    pub verify: Option<String>,
+    /// Failed attempts recorded with `tasks fail`.
+    pub attempts: i64,
+    /// Unix timestamp before which `claim-next` skips the task.
+    pub not_before: Option<i64>,
+    /// Unix timestamp the task should be closed by.
+    pub due_at: Option<i64>,
+    /// Lowest priority among the task and its open transitive descendants.
+    /// Only `show` and `claim-next` fill it in.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effective_priority: Option<i64>,
+    pub created_at: i64,
+    pub updated_at: i64,
     pub parents: Vec<i64>,
     pub children: Vec<i64>,
+    /// Acceptance criteria; `tasks close` refuses while any is unchecked.
+    pub criteria: Vec<CriterionRow>,
+    /// Parents that are not closed yet. Only used for display; JSON
+    /// consumers can derive it from `parents`.
+    #[serde(skip)]
+    pub blocked_by: Vec<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchHit {
+    #[serde(flatten)]
+    pub task: TaskRow,
+    pub snippet: String,
 }
 
 impl TaskRow {
@@ -364,9 +1039,15 @@ impl TaskRow {
         let desc = self.desc.as_deref().unwrap_or("");
         let parents = format_ids(&self.parents);
         let children = format_ids(&self.children);
+        let key = self
+            .key
+            .as_deref()
+            .map(|key| format!(" ({key})"))
+            .unwrap_or_default();
         format!(
-            "#{id} [{state}] p{priority} {title} - {desc} parents={parents} children={children}",
+            "#{id}{key} [{state}] p{priority} {title} - {desc} parents={parents} children={children}",
             id = self.id,
+            key = key,
             state = self.state,
             priority = self.priority,
             title = title,
@@ -375,17 +1056,165 @@ impl TaskRow {
             children = children
         )
     }
+
+    /// [`TaskRow::display_line`] followed by the due date and the numbered
+    /// acceptance criteria.
+    pub fn display_detail(&self) -> String {
+        let mut out = self.display_line();
+        if let Some(effective) = self.effective_priority.filter(|p| *p < self.priority) {
+            out.push_str(&format!("\n  effective priority p{effective} (inherited)"));
+        }
+        if let Some(due) = self.due_label(unix_now()) {
+            out.push_str(&format!("\n  {due}"));
+        }
+        for (index, criterion) in self.criteria.iter().enumerate() {
+            let mark = if criterion.checked { 'x' } else { ' ' };
+            out.push_str(&format!("\n  {}. [{mark}] {}", index + 1, criterion.text));
+        }
+        out
+    }
+
+    /// Open and past its due date.
+    pub fn is_overdue(&self, now: i64) -> bool {
+        self.state != "closed" && self.due_at.is_some_and(|due_at| due_at < now)
+    }
+
+    /// `overdue by 2h`, `due in 3d`, or `due 1d ago` for closed tasks.
+    pub fn due_label(&self, now: i64) -> Option<String> {
+        let due_at = self.due_at?;
+        let label = if self.is_overdue(now) {
+            format!("overdue by {}", format_span(now - due_at))
+        } else if due_at >= now {
+            format!("due in {}", format_span(due_at - now))
+        } else {
+            format!("due {} ago", format_span(now - due_at))
+        };
+        Some(label)
+    }
+
+    pub fn unchecked_criteria(&self) -> usize {
+        self.criteria
+            .iter()
+            .filter(|criterion| !criterion.checked)
+            .count()
+    }
+}
+
+impl TaskSort {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaskSort::Priority => "priority",
+            TaskSort::Id => "id",
+            TaskSort::Created => "created",
+            TaskSort::Updated => "updated",
+        }
+    }
+
+    fn column(&self) -> Task {
+        match self {
+            TaskSort::Priority => Task::Priority,
+            TaskSort::Id => Task::Id,
+            TaskSort::Created => Task::CreatedAt,
+            TaskSort::Updated => Task::UpdatedAt,
+        }
+    }
 }
 
 impl TaskState {
+    pub const ALL: [TaskState; 6] = [
+        TaskState::Ready,
+        TaskState::Blocked,
+        TaskState::Deferred,
+        TaskState::InProgress,
+        TaskState::NeedsHuman,
+        TaskState::Closed,
+    ];
+
     pub fn as_str(&self) -> &'static str {
         match self {
             TaskState::Ready => "ready",
             TaskState::Blocked => "blocked",
+            TaskState::Deferred => "deferred",
             TaskState::InProgress => "in_progress",
+            TaskState::NeedsHuman => "needs_human",
             TaskState::Closed => "closed",
         }
     }
+
+    /// `blocked` and `deferred` are worked out from a task's parents and
+    /// `not_before` time when it is read, so they are never stored.
+    pub fn is_derived(&self) -> bool {
+        matches!(self, TaskState::Blocked | TaskState::Deferred)
+    }
+
+    pub fn parse(value: &str) -> Option<TaskState> {
+        match value {
+            "ready" => Some(TaskState::Ready),
+            "blocked" => Some(TaskState::Blocked),
+            "deferred" => Some(TaskState::Deferred),
+            "in_progress" => Some(TaskState::InProgress),
+            "needs_human" => Some(TaskState::NeedsHuman),
+            "closed" => Some(TaskState::Closed),
+            _ => None,
+        }
+    }
+}
+
+/// Columns read into a [`TaskRow`], in the order [`task_row`] expects.
+fn task_columns() -> [(Task, Task); 12] {
+    [
+        (Task::Table, Task::Id),
+        (Task::Table, Task::Title),
+        (Task::Table, Task::Desc),
+        (Task::Table, Task::Priority),
+        (Task::Table, Task::State),
+        (Task::Table, Task::Key),
+        (Task::Table, Task::CreatedAt),
+        (Task::Table, Task::UpdatedAt),
+        (Task::Table, Task::Verify),
+        (Task::Table, Task::Attempts),
+        (Task::Table, Task::NotBefore),
+        (Task::Table, Task::DueAt),
+    ]
+}
+
+fn task_row(row: &QueryResult) -> Result<TaskRow, DbErr> {
+    Ok(TaskRow {
+        id: row.try_get_by_index(0)?,
+        title: row.try_get_by_index(1)?,
+        desc: row.try_get_by_index(2)?,
+        priority: row.try_get_by_index(3)?,
+        state: row.try_get_by_index(4)?,
+        key: row.try_get_by_index(5)?,
+        created_at: row.try_get_by_index(6)?,
+        updated_at: row.try_get_by_index(7)?,
+        verify: row.try_get_by_index(8)?,
+        attempts: row.try_get_by_index(9)?,
+        not_before: row.try_get_by_index(10)?,
+        due_at: row.try_get_by_index(11)?,
+        effective_priority: None,
+        parents: Vec::new(),
+        children: Vec::new(),
+        criteria: Vec::new(),
+        blocked_by: Vec::new(),
+    })
+}
+
+/// A span of seconds in its largest whole unit, e.g. `3d` or `45m`.
+fn format_span(seconds: i64) -> String {
+    match seconds {
+        s if s >= 86_400 => format!("{}d", s / 86_400),
+        s if s >= 3_600 => format!("{}h", s / 3_600),
+        s if s >= 60 => format!("{}m", s / 60),
+        s => format!("{s}s"),
+    }
+}
+
+pub(crate) fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|now| now.as_secs() as i64)
+        .unwrap_or_default()
 }
 
 fn format_ids(values: &[i64]) -> String {
@@ -450,6 +1279,8 @@ async fn populate_dependencies(
 
     let parent_state_map =
         fetch_task_states(conn, &parent_ids.into_iter().collect::<Vec<_>>()).await?;
+    let now = unix_now();
+    let mut criteria = criteria::load_criteria(conn, ids).await?;
 
     for task in tasks.iter_mut() {
         if let Some(parents) = parents_by_child.get(&task.id) {
@@ -460,16 +1291,22 @@ async fn populate_dependencies(
             task.children = children.clone();
             task.children.sort_unstable();
         }
+        task.criteria = criteria.remove(&task.id).unwrap_or_default();
 
-        if task.state != "closed" {
-            let blocked = task.parents.iter().any(|parent_id| {
+        task.blocked_by = task
+            .parents
+            .iter()
+            .copied()
+            .filter(|parent_id| {
                 parent_state_map
                     .get(parent_id)
                     .is_some_and(|state| state != "closed")
-            });
-            if blocked {
-                task.state = "blocked".to_string();
-            }
+            })
+            .collect();
+        if task.state != "closed" && !task.blocked_by.is_empty() {
+            task.state = "blocked".to_string();
+        } else if task.state == "ready" && task.not_before.is_some_and(|at| at > now) {
+            task.state = "deferred".to_string();
         }
     }
 
@@ -527,8 +1364,8 @@ mod tests {
         let db_path = temp.path().join("pearls.db");
         let conn = conn::connect(&db_path).await.expect("connect");
 
-        let t1 = add_task(&conn, "one", "first", None).await.expect("add t1");
-        let t2 = add_task(&conn, "two", "second", None)
+        let t1 = add_task(&conn, "one", "first", None, None).await.expect("add t1");
+        let t2 = add_task(&conn, "two", "second", None, None)
             .await
             .expect("add t2");
         update_metadata(&conn, t2.id, None, None, None, Some(TaskState::InProgress))
@@ -551,10 +1388,10 @@ mod tests {
         let db_path = temp.path().join("pearls.db");
         let conn = conn::connect(&db_path).await.expect("connect");
 
-        let parent = add_task(&conn, "parent", "p", None)
+        let parent = add_task(&conn, "parent", "p", None, None)
             .await
             .expect("add parent");
-        let child = add_task(&conn, "child", "c", None)
+        let child = add_task(&conn, "child", "c", None, None)
             .await
             .expect("add child");
 
@@ -581,20 +1418,484 @@ mod tests {
         let db_path = temp.path().join("pearls.db");
         let conn = conn::connect(&db_path).await.expect("connect");
 
-        let low = add_task(&conn, "low", "p2", Some(2)).await.expect("add low");
-        let high = add_task(&conn, "high", "p1", Some(1)).await.expect("add high");
-        let tied = add_task(&conn, "tied", "p1", Some(1))
+        let low = add_task(&conn, "low", "p2", Some(2), None).await.expect("add low");
+        let high = add_task(&conn, "high", "p1", Some(1), None).await.expect("add high");
+        let tied = add_task(&conn, "tied", "p1", Some(1), None)
             .await
             .expect("add tied");
 
-        let first = claim_next(&conn).await.expect("claim first");
+        let first = claim_next(&conn, Schedule::Priority)
+            .await
+            .expect("claim first");
         assert_eq!(first.as_ref().map(|task| task.id), Some(high.id));
         assert_eq!(first.as_ref().map(|task| task.state.as_str()), Some("in_progress"));
 
-        let second = claim_next(&conn).await.expect("claim second");
+        let second = claim_next(&conn, Schedule::Priority)
+            .await
+            .expect("claim second");
         assert_eq!(second.as_ref().map(|task| task.id), Some(tied.id));
 
-        let third = claim_next(&conn).await.expect("claim third");
+        let third = claim_next(&conn, Schedule::Priority)
+            .await
+            .expect("claim third");
         assert_eq!(third.as_ref().map(|task| task.id), Some(low.id));
     }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn deadline_schedule_claims_overdue_then_due_soon_tasks_first() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let db_path = temp.path().join("pearls.db");
+        let conn = conn::connect(&db_path).await.expect("connect");
+
+        let urgent = add_task(&conn, "urgent", "u", Some(1), None)
+            .await
+            .expect("add urgent");
+        let later = add_task(&conn, "later", "l", Some(3), None)
+            .await
+            .expect("add later");
+        let soon = add_task(&conn, "soon", "s", Some(3), None)
+            .await
+            .expect("add soon");
+        let overdue = add_task(&conn, "overdue", "o", Some(5), None)
+            .await
+            .expect("add overdue");
+        let now = unix_now();
+        for (id, due_at) in [
+            (later.id, now + 7 * DUE_SOON),
+            (soon.id, now + 3600),
+            (overdue.id, now - 60),
+        ] {
+            set_due_at(&conn, id, Some(due_at)).await.expect("set due");
+        }
+
+        let filter = TaskFilter {
+            overdue: true,
+            ..TaskFilter::default()
+        };
+        let counts = count_tasks_by_state(&conn, &filter)
+            .await
+            .expect("count overdue");
+        assert_eq!(counts.values().sum::<u64>(), 1);
+
+        let mut claimed = Vec::new();
+        while let Some(task) = claim_next(&conn, Schedule::Deadline).await.expect("claim") {
+            claimed.push(task.id);
+        }
+        assert_eq!(claimed, vec![overdue.id, soon.id, urgent.id, later.id]);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn inherited_schedule_claims_blockers_of_important_work_first() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let db_path = temp.path().join("pearls.db");
+        let conn = conn::connect(&db_path).await.expect("connect");
+
+        let unrelated = add_task(&conn, "unrelated", "u", Some(2), None)
+            .await
+            .expect("add unrelated");
+        let blocker = add_task(&conn, "blocker", "b", Some(5), None)
+            .await
+            .expect("add blocker");
+        let middle = add_task(&conn, "middle", "m", Some(4), None)
+            .await
+            .expect("add middle");
+        let important = add_task(&conn, "important", "i", Some(1), None)
+            .await
+            .expect("add important");
+        let done = add_task(&conn, "done", "d", Some(3), None)
+            .await
+            .expect("add done");
+        let behind_done = add_task(&conn, "behind done", "b", Some(0), None)
+            .await
+            .expect("add behind done");
+        add_dependency(&conn, blocker.id, middle.id)
+            .await
+            .expect("blocker -> middle");
+        add_dependency(&conn, middle.id, important.id)
+            .await
+            .expect("middle -> important");
+        add_dependency(&conn, blocker.id, done.id)
+            .await
+            .expect("blocker -> done");
+        add_dependency(&conn, done.id, behind_done.id)
+            .await
+            .expect("done -> behind done");
+        update_metadata(
+            &conn,
+            behind_done.id,
+            None,
+            None,
+            None,
+            Some(TaskState::InProgress),
+        )
+        .await
+        .expect("start behind done");
+        update_metadata(&conn, done.id, None, None, None, Some(TaskState::Closed))
+            .await
+            .expect("close done");
+
+        let effective = effective_priorities(&conn, &[unrelated.id, blocker.id, middle.id])
+            .await
+            .expect("effective");
+        assert_eq!(effective[&unrelated.id], 2);
+        assert_eq!(effective[&blocker.id], 1);
+        assert_eq!(effective[&middle.id], 1);
+
+        let claimed = claim_next(&conn, Schedule::Inherited)
+            .await
+            .expect("claim inherited")
+            .expect("a task");
+        assert_eq!(claimed.id, blocker.id);
+        assert_eq!(claimed.effective_priority, Some(1));
+
+        let claimed = claim_next(&conn, Schedule::Priority)
+            .await
+            .expect("claim priority")
+            .expect("a task");
+        assert_eq!(claimed.id, unrelated.id);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn future_not_before_defers_task_until_it_passes() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let db_path = temp.path().join("pearls.db");
+        let conn = conn::connect(&db_path).await.expect("connect");
+
+        let later = add_task(&conn, "later", "l", Some(1), None)
+            .await
+            .expect("add later");
+        let now = add_task(&conn, "now", "n", Some(2), None)
+            .await
+            .expect("add now");
+        set_not_before(&conn, later.id, Some(unix_now() + 3600))
+            .await
+            .expect("defer");
+
+        let deferred = list_tasks(&conn, &[TaskState::Deferred])
+            .await
+            .expect("list deferred");
+        assert_eq!(deferred.len(), 1);
+        assert_eq!(deferred[0].id, later.id);
+        let counts = count_tasks_by_state(&conn, &TaskFilter::default())
+            .await
+            .expect("count");
+        assert_eq!(counts["deferred"], 1);
+        assert_eq!(counts["ready"], 1);
+        assert_eq!(
+            next_claimable_at(&conn).await.expect("next"),
+            deferred[0].not_before
+        );
+
+        let first = claim_next(&conn, Schedule::Priority)
+            .await
+            .expect("claim first");
+        assert_eq!(first.map(|task| task.id), Some(now.id));
+        assert!(
+            claim_next(&conn, Schedule::Priority)
+                .await
+                .expect("claim none")
+                .is_none()
+        );
+
+        set_not_before(&conn, later.id, Some(unix_now() - 1))
+            .await
+            .expect("start");
+        let second = claim_next(&conn, Schedule::Priority)
+            .await
+            .expect("claim second");
+        assert_eq!(second.map(|task| task.id), Some(later.id));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn list_tasks_paginated_filters_sorts_and_counts() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let db_path = temp.path().join("pearls.db");
+        let conn = conn::connect(&db_path).await.expect("connect");
+
+        let root = add_task(&conn, "Parser epic", "root", Some(3), None)
+            .await
+            .expect("add root");
+        let child = add_task(&conn, "Lexer", "child", Some(1), None)
+            .await
+            .expect("add child");
+        let grandchild = add_task(&conn, "Lexer_tests", "grandchild", Some(2), None)
+            .await
+            .expect("add grandchild");
+        let other = add_task(&conn, "Docs", "other", Some(5), None)
+            .await
+            .expect("add other");
+        add_dependency(&conn, root.id, child.id)
+            .await
+            .expect("root -> child");
+        add_dependency(&conn, child.id, grandchild.id)
+            .await
+            .expect("child -> grandchild");
+
+        let ids = |page: TaskPage| page.items.iter().map(|task| task.id).collect::<Vec<_>>();
+        let by_id = PageRequest {
+            sort: TaskSort::Id,
+            descending: false,
+            offset: 0,
+            limit: 10,
+            cursor: None,
+        };
+
+        let by_priority = PageRequest {
+            sort: TaskSort::Priority,
+            descending: true,
+            offset: 0,
+            limit: 10,
+            cursor: None,
+        };
+        let by_priority = list_tasks_paginated(&conn, &TaskFilter::default(), &by_priority)
+            .await
+            .expect("sort by priority");
+        assert_eq!(ids(by_priority), vec![other.id, root.id, grandchild.id, child.id]);
+
+        let filter = TaskFilter {
+            descendant_of: Some(root.id),
+            priority_max: Some(2),
+            ..TaskFilter::default()
+        };
+        let descendants = list_tasks_paginated(&conn, &filter, &by_id)
+            .await
+            .expect("descendants");
+        assert_eq!(ids(descendants), vec![child.id, grandchild.id]);
+
+        let filter = TaskFilter {
+            title_contains: vec!["lexer_".to_string()],
+            ..TaskFilter::default()
+        };
+        let titled = list_tasks_paginated(&conn, &filter, &by_id)
+            .await
+            .expect("title contains");
+        assert_eq!(ids(titled), vec![grandchild.id]);
+
+        // State filters use the derived state, so blocked children are not "ready".
+        let filter = TaskFilter {
+            states: vec![TaskState::Ready],
+            ..TaskFilter::default()
+        };
+        let ready = list_tasks_paginated(&conn, &filter, &by_id)
+            .await
+            .expect("ready");
+        assert_eq!(ids(ready), vec![root.id, other.id]);
+
+        let filter = TaskFilter {
+            has_children: true,
+            ..TaskFilter::default()
+        };
+        let counts = count_tasks_by_state(&conn, &filter)
+            .await
+            .expect("count");
+        assert_eq!(counts["ready"], 1);
+        assert_eq!(counts["blocked"], 1);
+        assert_eq!(counts["closed"], 0);
+
+        let unlimited = PageRequest {
+            limit: u64::MAX,
+            ..by_id
+        };
+        let all = list_tasks_paginated(&conn, &TaskFilter::default(), &unlimited)
+            .await
+            .expect("unlimited");
+        assert_eq!(all.items.len(), 4);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn list_tasks_paginated_walks_pages_with_cursor() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let db_path = temp.path().join("pearls.db");
+        let conn = conn::connect(&db_path).await.expect("connect");
+
+        let mut expected = Vec::new();
+        for priority in [2, 1, 2, 1, 3] {
+            let task = add_task(&conn, "task", "t", Some(priority), None)
+                .await
+                .expect("add task");
+            expected.push((priority, task.id));
+        }
+        expected.sort();
+
+        let mut page = PageRequest {
+            sort: TaskSort::Priority,
+            descending: false,
+            offset: 0,
+            limit: 2,
+            cursor: None,
+        };
+        let mut seen = Vec::new();
+        loop {
+            let result = list_tasks_paginated(&conn, &TaskFilter::default(), &page)
+                .await
+                .expect("page");
+            assert_eq!(result.total, 5);
+            seen.extend(result.items.iter().map(|task| (task.priority, task.id)));
+            match result.next_cursor {
+                Some(cursor) => page.cursor = Some(cursor),
+                None => break,
+            }
+        }
+        assert_eq!(seen, expected);
+
+        page.descending = true;
+        list_tasks_paginated(&conn, &TaskFilter::default(), &page)
+            .await
+            .expect_err("cursor from ascending page");
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn for_each_task_streams_across_chunks_and_stops_early() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let db_path = temp.path().join("pearls.db");
+        let conn = conn::connect(&db_path).await.expect("connect");
+
+        let total = STREAM_CHUNK + 20;
+        conn.execute_unprepared(&format!(
+            "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < {total}) \
+             INSERT INTO task (title, desc, priority, state) \
+             SELECT 'task ' || i, 'bulk', 1, 'ready' FROM n"
+        ))
+        .await
+        .expect("bulk insert");
+
+        let mut page = PageRequest {
+            sort: TaskSort::Id,
+            descending: false,
+            offset: 0,
+            limit: u64::MAX,
+            cursor: None,
+        };
+        let mut ids = Vec::new();
+        for_each_task(&conn, &TaskFilter::default(), &page, |task| {
+            ids.push(task.id);
+            true
+        })
+        .await
+        .expect("stream all");
+        assert_eq!(ids, (1..=total as i64).collect::<Vec<_>>());
+
+        page.limit = STREAM_CHUNK + 5;
+        let mut visited = 0;
+        for_each_task(&conn, &TaskFilter::default(), &page, |_| {
+            visited += 1;
+            true
+        })
+        .await
+        .expect("stream limited");
+        assert_eq!(visited, STREAM_CHUNK + 5);
+
+        let mut visited = 0;
+        for_each_task(&conn, &TaskFilter::default(), &page, |_| {
+            visited += 1;
+            visited < 3
+        })
+        .await
+        .expect("stream stopped");
+        assert_eq!(visited, 3);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn task_keys_are_unique_and_resolve_to_ids() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let db_path = temp.path().join("pearls.db");
+        let conn = conn::connect(&db_path).await.expect("connect");
+
+        let keyed = add_task(&conn, "keyed", "k", None, Some("build-parser"))
+            .await
+            .expect("add keyed");
+        add_task(&conn, "unkeyed", "u", None, None)
+            .await
+            .expect("add unkeyed");
+        add_task(&conn, "also unkeyed", "u", None, None)
+            .await
+            .expect("nulls don't collide");
+        add_task(&conn, "dup", "d", None, Some("build-parser"))
+            .await
+            .expect_err("duplicate key");
+
+        let id = resolve_ref(&conn, &TaskRef::Key("build-parser".to_string()))
+            .await
+            .expect("resolve key");
+        assert_eq!(id, keyed.id);
+        resolve_ref(&conn, &TaskRef::Key("missing".to_string()))
+            .await
+            .expect_err("unknown key");
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn search_tasks_ranks_matches_and_tracks_updates() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let db_path = temp.path().join("pearls.db");
+        let conn = conn::connect(&db_path).await.expect("connect");
+
+        let desc_only = add_task(&conn, "Write docs", "mention the parser flags", None, None)
+            .await
+            .expect("add docs");
+        let title = add_task(&conn, "Build parser", "tokens to AST", None, None)
+            .await
+            .expect("add parser");
+        add_task(&conn, "Unrelated", "nothing here", None, None)
+            .await
+            .expect("add unrelated");
+
+        let hits = search_tasks(&conn, "parsing", &[], 10, ("[", "]"))
+            .await
+            .expect("search");
+        let ids: Vec<i64> = hits.iter().map(|hit| hit.task.id).collect();
+        assert_eq!(ids, vec![title.id, desc_only.id]);
+        assert!(hits[0].snippet.contains("[parser]"));
+
+        update_metadata(
+            &conn,
+            title.id,
+            Some("Build lexer"),
+            Some("tokens"),
+            None,
+            Some(TaskState::Closed),
+        )
+        .await
+        .expect("update");
+        let hits = search_tasks(&conn, "lexer", &[TaskState::Closed], 10, ("[", "]"))
+            .await
+            .expect("search updated");
+        assert_eq!(hits.len(), 1);
+        let hits = search_tasks(&conn, "parser", &[TaskState::Ready], 10, ("[", "]"))
+            .await
+            .expect("search ready");
+        let ids: Vec<i64> = hits.iter().map(|hit| hit.task.id).collect();
+        assert_eq!(ids, vec![desc_only.id]);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn search_tasks_finds_words_that_only_appear_in_notes() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let db_path = temp.path().join("pearls.db");
+        let conn = conn::connect(&db_path).await.expect("connect");
+
+        let task = add_task(&conn, "Fix CI", "make the build green", None, None)
+            .await
+            .expect("add task");
+        add_task(&conn, "Other", "nothing here", None, None)
+            .await
+            .expect("add other");
+        notes::add_note(&conn, task.id, "verify failed: segfault in linker", None)
+            .await
+            .expect("add note");
+
+        let hits = search_tasks(&conn, "segfault", &[], 10, ("[", "]"))
+            .await
+            .expect("search notes");
+        let ids: Vec<i64> = hits.iter().map(|hit| hit.task.id).collect();
+        assert_eq!(ids, vec![task.id]);
+        assert!(hits[0].snippet.contains("[segfault]"));
+
+        notes::add_note(&conn, task.id, "second try: linker fixed", None)
+            .await
+            .expect("add second note");
+        let hits = search_tasks(&conn, "segfault fixed", &[], 10, ("[", "]"))
+            .await
+            .expect("search both notes");
+        assert_eq!(hits.len(), 1);
+    }
 }
@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use schemars::JsonSchema;
+use sea_orm_migration::prelude::ConnectionTrait;
+use sea_orm_migration::sea_orm::{DatabaseConnection, DbBackend, DbErr, Statement};
+use serde::Serialize;
+
+use crate::cli::TaskState;
+use crate::db::tasks::{self, TaskRow};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Added,
+    Claimed,
+    Closed,
+    Unblocked,
+    Updated,
+}
+
+impl EventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::Added => "added",
+            EventKind::Claimed => "claimed",
+            EventKind::Closed => "closed",
+            EventKind::Unblocked => "unblocked",
+            EventKind::Updated => "updated",
+        }
+    }
+}
+
+/// A change to one task between two snapshots, with the task as it is now.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TaskEvent {
+    pub event: EventKind,
+    pub task: TaskRow,
+}
+
+/// Tasks keyed by id, as last seen by the watcher.
+pub type Snapshot = HashMap<i64, TaskRow>;
+
+/// SQLite's `data_version`, which changes whenever another connection
+/// commits. Only comparable between calls on the same connection, so use it
+/// with [`crate::db::conn::connect_single`].
+pub async fn data_version(conn: &DatabaseConnection) -> Result<i64, DbErr> {
+    let row = conn
+        .query_one(Statement::from_string(
+            DbBackend::Sqlite,
+            "PRAGMA data_version".to_string(),
+        ))
+        .await?
+        .ok_or_else(|| DbErr::Custom("failed to read data_version".to_string()))?;
+    row.try_get_by_index(0)
+}
+
+/// Sleeps in `interval` steps until `data_version` moves past `version`.
+/// Returns `false` if `deadline` passes first.
+pub async fn wait_for_change(
+    conn: &DatabaseConnection,
+    version: i64,
+    interval: Duration,
+    deadline: Option<Instant>,
+) -> Result<bool, DbErr> {
+    loop {
+        let step = match deadline {
+            Some(deadline) => {
+                let left = deadline.saturating_duration_since(Instant::now());
+                if left.is_zero() {
+                    return Ok(false);
+                }
+                left.min(interval)
+            }
+            None => interval,
+        };
+        tokio::time::sleep(step).await;
+        if data_version(conn).await? != version {
+            return Ok(true);
+        }
+    }
+}
+
+pub async fn snapshot(conn: &DatabaseConnection) -> Result<Snapshot, DbErr> {
+    let tasks = tasks::list_tasks(conn, &TaskState::ALL).await?;
+    Ok(tasks.into_iter().map(|task| (task.id, task)).collect())
+}
+
+/// Events that turn `before` into `after`, ordered by task id. Each task
+/// yields at most one event; state transitions win over field edits.
+pub fn diff(before: &Snapshot, after: &Snapshot) -> Vec<TaskEvent> {
+    let mut tasks: Vec<&TaskRow> = after.values().collect();
+    tasks.sort_by_key(|task| task.id);
+
+    let mut events = Vec::new();
+    for task in tasks {
+        let event = match before.get(&task.id) {
+            None => Some(EventKind::Added),
+            Some(old) if old.state != task.state => Some(match task.state.as_str() {
+                "in_progress" => EventKind::Claimed,
+                "closed" => EventKind::Closed,
+                "ready" if old.state == "blocked" || old.state == "deferred" => {
+                    EventKind::Unblocked
+                }
+                _ => EventKind::Updated,
+            }),
+            Some(old) if !same_fields(old, task) => Some(EventKind::Updated),
+            Some(_) => None,
+        };
+        if let Some(event) = event {
+            events.push(TaskEvent {
+                event,
+                task: task.clone(),
+            });
+        }
+    }
+    events
+}
+
+fn same_fields(a: &TaskRow, b: &TaskRow) -> bool {
+    a.title == b.title
+        && a.desc == b.desc
+        && a.priority == b.priority
+        && a.key == b.key
+        && a.verify == b.verify
+        && a.not_before == b.not_before
+        && a.due_at == b.due_at
+        && a.criteria == b.criteria
+        && a.parents == b.parents
+        && a.children == b.children
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::conn;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn diff_reports_transitions_seen_through_data_version() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let db_path = temp.path().join("pearls.db");
+        let writer = conn::connect(&db_path).await.expect("connect writer");
+        let watcher = conn::connect_single(&db_path)
+            .await
+            .expect("connect watcher");
+
+        let parent = tasks::add_task(&writer, "parent", "p", None, None)
+            .await
+            .expect("add parent");
+        let child = tasks::add_task(&writer, "child", "c", None, None)
+            .await
+            .expect("add child");
+        tasks::add_dependency(&writer, parent.id, child.id)
+            .await
+            .expect("add dependency");
+        let later = tasks::add_task(&writer, "later", "l", None, None)
+            .await
+            .expect("add later");
+        tasks::set_not_before(&writer, later.id, Some(tasks::unix_now() + 3600))
+            .await
+            .expect("defer later");
+        let version = data_version(&watcher).await.expect("version");
+        let before = snapshot(&watcher).await.expect("snapshot");
+
+        assert_eq!(
+            data_version(&watcher).await.expect("unchanged version"),
+            version
+        );
+        tasks::update_metadata(
+            &writer,
+            parent.id,
+            None,
+            None,
+            None,
+            Some(TaskState::Closed),
+        )
+        .await
+        .expect("close parent");
+        tasks::set_not_before(&writer, later.id, None)
+            .await
+            .expect("undefer later");
+        let added = tasks::add_task(&writer, "new", "n", None, None)
+            .await
+            .expect("add new");
+        assert_ne!(
+            data_version(&watcher).await.expect("changed version"),
+            version
+        );
+
+        let events: Vec<(EventKind, i64)> =
+            diff(&before, &snapshot(&watcher).await.expect("after"))
+                .into_iter()
+                .map(|event| (event.event, event.task.id))
+                .collect();
+        assert_eq!(
+            events,
+            vec![
+                (EventKind::Closed, parent.id),
+                (EventKind::Unblocked, child.id),
+                (EventKind::Unblocked, later.id),
+                (EventKind::Added, added.id),
+            ]
+        );
+    }
+}
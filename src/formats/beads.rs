@@ -0,0 +1,223 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde_json::Value;
+
+use crate::db::dump::{DUMP_VERSION, Dump, DumpDependency, DumpTask};
+
+/// Issue fields that map directly onto pearls columns or dependencies.
+const MAPPED_FIELDS: [&str; 6] = [
+    "id",
+    "title",
+    "description",
+    "status",
+    "priority",
+    "dependencies",
+];
+
+pub struct BeadsImport {
+    pub dump: Dump,
+    /// Beads issue ids, indexed by the dump id assigned to each issue minus one.
+    pub source_ids: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// Converts a beads `issues.jsonl` export into a dump. Issues get
+/// document-order ids; `blocks` dependencies become parent -> child edges and
+/// everything that has no pearls equivalent is summarized in `warnings`.
+pub fn parse(contents: &str) -> Result<BeadsImport, String> {
+    let mut issues = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let value: Value =
+            serde_json::from_str(line).map_err(|err| format!("line {}: {err}", index + 1))?;
+        let Value::Object(issue) = value else {
+            return Err(format!("line {}: expected a JSON object", index + 1));
+        };
+        issues.push(issue);
+    }
+
+    let mut ids_by_source: HashMap<String, i64> = HashMap::new();
+    let mut source_ids = Vec::with_capacity(issues.len());
+    for (index, issue) in issues.iter().enumerate() {
+        let source = issue
+            .get("id")
+            .and_then(Value::as_str)
+            .ok_or_else(|| format!("issue {} is missing a string id", index + 1))?;
+        if ids_by_source
+            .insert(source.to_string(), index as i64 + 1)
+            .is_some()
+        {
+            return Err(format!("duplicate beads id {source}"));
+        }
+        source_ids.push(source.to_string());
+    }
+
+    let mut unmapped_fields: BTreeMap<String, usize> = BTreeMap::new();
+    let mut unmapped_statuses: BTreeMap<String, usize> = BTreeMap::new();
+    let mut skipped_types: BTreeMap<String, usize> = BTreeMap::new();
+    let mut warnings = Vec::new();
+    let mut tasks = Vec::with_capacity(issues.len());
+    let mut dependencies = Vec::new();
+
+    for (index, issue) in issues.iter().enumerate() {
+        let id = index as i64 + 1;
+        let source = &source_ids[index];
+
+        for (key, value) in issue {
+            if !MAPPED_FIELDS.contains(&key.as_str()) && !is_empty(value) {
+                *unmapped_fields.entry(key.clone()).or_default() += 1;
+            }
+        }
+
+        let status = issue
+            .get("status")
+            .and_then(Value::as_str)
+            .unwrap_or("open");
+        let state = match status {
+            "open" => "ready",
+            "in_progress" => "in_progress",
+            // `blocked` is derived from parents, which the `blocks`
+            // dependencies bring along.
+            "blocked" => "ready",
+            "closed" => "closed",
+            other => {
+                *unmapped_statuses.entry(other.to_string()).or_default() += 1;
+                "ready"
+            }
+        };
+
+        tasks.push(DumpTask {
+            id,
+            title: issue
+                .get("title")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            desc: issue
+                .get("description")
+                .and_then(Value::as_str)
+                .filter(|desc| !desc.is_empty())
+                .map(str::to_string),
+            priority: issue.get("priority").and_then(Value::as_i64).unwrap_or(1),
+            state: state.to_string(),
+            key: None,
+            verify: None,
+            attempts: 0,
+            not_before: None,
+            due_at: None,
+            criteria: Vec::new(),
+            created_at: 0,
+            updated_at: 0,
+        });
+
+        let Some(deps) = issue.get("dependencies").and_then(Value::as_array) else {
+            continue;
+        };
+        for dep in deps {
+            let kind = dep.get("type").and_then(Value::as_str).unwrap_or("blocks");
+            if kind != "blocks" {
+                *skipped_types.entry(kind.to_string()).or_default() += 1;
+                continue;
+            }
+
+            let child = dep
+                .get("issue_id")
+                .and_then(Value::as_str)
+                .unwrap_or(source);
+            let Some(parent) = dep.get("depends_on_id").and_then(Value::as_str) else {
+                warnings.push(format!(
+                    "{source}: skipped dependency without depends_on_id"
+                ));
+                continue;
+            };
+            match (ids_by_source.get(parent), ids_by_source.get(child)) {
+                (Some(parent_id), Some(child_id)) => dependencies.push(DumpDependency {
+                    parent_id: *parent_id,
+                    child_id: *child_id,
+                }),
+                _ => warnings.push(format!(
+                    "{source}: skipped dependency {child} -> {parent} on an issue not in the file"
+                )),
+            }
+        }
+    }
+
+    for (field, count) in unmapped_fields {
+        warnings.push(format!("unmapped field `{field}` on {count} issue(s)"));
+    }
+    for (status, count) in unmapped_statuses {
+        warnings.push(format!(
+            "unmapped status `{status}` on {count} issue(s), imported as ready"
+        ));
+    }
+    for (kind, count) in skipped_types {
+        warnings.push(format!("skipped {count} `{kind}` dependency(ies)"));
+    }
+
+    Ok(BeadsImport {
+        dump: Dump {
+            version: DUMP_VERSION,
+            tasks,
+            dependencies,
+            notes: Vec::new(),
+        },
+        source_ids,
+        warnings,
+    })
+}
+
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::String(text) => text.is_empty(),
+        Value::Array(items) => items.is_empty(),
+        Value::Object(map) => map.is_empty(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_maps_statuses_and_blocks_dependencies() {
+        let contents = r#"{"id":"bd-1","title":"Schema","description":"Design it","status":"closed","priority":0,"issue_type":"task"}
+{"id":"bd-2","title":"API","status":"open","priority":2,"labels":["backend"],"dependencies":[{"issue_id":"bd-2","depends_on_id":"bd-1","type":"blocks"},{"issue_id":"bd-2","depends_on_id":"bd-1","type":"related"}]}
+{"id":"bd-3","title":"Later","status":"deferred","labels":[]}
+{"id":"bd-4","title":"Docs","status":"blocked","dependencies":[{"issue_id":"bd-4","depends_on_id":"bd-2","type":"blocks"}]}
+"#;
+
+        let import = parse(contents).expect("parse");
+        assert_eq!(import.source_ids, ["bd-1", "bd-2", "bd-3", "bd-4"]);
+
+        let states: Vec<&str> = import
+            .dump
+            .tasks
+            .iter()
+            .map(|task| task.state.as_str())
+            .collect();
+        assert_eq!(states, ["closed", "ready", "ready", "ready"]);
+        assert_eq!(import.dump.tasks[0].priority, 0);
+        assert_eq!(import.dump.tasks[0].desc.as_deref(), Some("Design it"));
+
+        let edges: Vec<(i64, i64)> = import
+            .dump
+            .dependencies
+            .iter()
+            .map(|dep| (dep.parent_id, dep.child_id))
+            .collect();
+        assert_eq!(edges, [(1, 2), (2, 4)]);
+
+        assert_eq!(
+            import.warnings,
+            [
+                "unmapped field `issue_type` on 1 issue(s)",
+                "unmapped field `labels` on 1 issue(s)",
+                "unmapped status `deferred` on 1 issue(s), imported as ready",
+                "skipped 1 `related` dependency(ies)",
+            ]
+        );
+    }
+}
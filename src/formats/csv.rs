@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::cli::TaskState;
+use crate::db::dump::DumpTask;
+
+const HEADERS: [&str; 6] = ["id", "title", "desc", "priority", "state", "parents"];
+
+/// A row from an update CSV. Fields are `None` when their column is absent,
+/// so a sheet containing only `id,priority` leaves everything else alone.
+#[derive(Debug)]
+pub struct CsvRow {
+    pub line: u64,
+    pub id: i64,
+    pub title: Option<String>,
+    pub desc: Option<String>,
+    pub priority: Option<i64>,
+    pub state: Option<TaskState>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct FieldChange {
+    pub id: i64,
+    pub field: &'static str,
+    pub from: String,
+    pub to: String,
+}
+
+/// The changes one row would make, ready to hand to `update_metadata`.
+#[derive(Debug)]
+pub struct TaskUpdate {
+    pub id: i64,
+    pub title: Option<String>,
+    pub desc: Option<String>,
+    pub priority: Option<i64>,
+    pub state: Option<TaskState>,
+    pub changes: Vec<FieldChange>,
+}
+
+/// Renders stored tasks as CSV. `state` is the persisted state (never the
+/// derived `blocked`) so an unedited sheet imports back without changes;
+/// `parents` is informational and ignored on import.
+pub fn render(tasks: &[DumpTask], parents: &HashMap<i64, Vec<i64>>) -> Result<String, String> {
+    let mut writer = ::csv::Writer::from_writer(Vec::new());
+    writer
+        .write_record(HEADERS)
+        .map_err(|err| err.to_string())?;
+    for task in tasks {
+        let parent_ids = parents
+            .get(&task.id)
+            .map(|ids| {
+                ids.iter()
+                    .map(|id| id.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .unwrap_or_default();
+        writer
+            .write_record([
+                task.id.to_string(),
+                task.title.clone().unwrap_or_default(),
+                task.desc.clone().unwrap_or_default(),
+                task.priority.to_string(),
+                task.state.clone(),
+                parent_ids,
+            ])
+            .map_err(|err| err.to_string())?;
+    }
+    let bytes = writer.into_inner().map_err(|err| err.to_string())?;
+    String::from_utf8(bytes).map_err(|err| err.to_string())
+}
+
+pub fn parse(contents: &str) -> Result<Vec<CsvRow>, String> {
+    let mut reader = ::csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(contents.as_bytes());
+    let headers = reader.headers().map_err(|err| err.to_string())?.clone();
+    let column = |name: &str| headers.iter().position(|header| header.trim() == name);
+    let id_col = column("id").ok_or_else(|| "missing `id` column".to_string())?;
+    let title_col = column("title");
+    let desc_col = column("desc");
+    let priority_col = column("priority");
+    let state_col = column("state");
+
+    let mut rows = Vec::new();
+    let mut first_lines = HashMap::new();
+    for record in reader.records() {
+        let record = record.map_err(|err| err.to_string())?;
+        let line = record.position().map(|pos| pos.line()).unwrap_or_default();
+        let cell = |col: Option<usize>| col.map(|col| record.get(col).unwrap_or("").to_string());
+
+        let id = record
+            .get(id_col)
+            .unwrap_or("")
+            .trim()
+            .parse()
+            .map_err(|_| format!("line {line}: invalid id"))?;
+        if let Some(first) = first_lines.insert(id, line) {
+            return Err(format!(
+                "line {line}: task {id} already appears on line {first}"
+            ));
+        }
+        let priority = match cell(priority_col) {
+            Some(value) => Some(
+                value
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("line {line}: invalid priority {value:?}"))?,
+            ),
+            None => None,
+        };
+        let state = match cell(state_col) {
+            Some(value) => Some(
+                TaskState::parse(value.trim())
+                    .ok_or_else(|| format!("line {line}: unknown state {value:?}"))?,
+            ),
+            None => None,
+        };
+
+        rows.push(CsvRow {
+            line,
+            id,
+            title: cell(title_col),
+            desc: cell(desc_col),
+            priority,
+            state,
+        });
+    }
+    Ok(rows)
+}
+
+/// Compares a row against the stored task and keeps only the fields that
+/// differ. Empty text cells compare equal to a missing title/description.
+pub fn diff(row: &CsvRow, current: &DumpTask) -> TaskUpdate {
+    let mut update = TaskUpdate {
+        id: row.id,
+        title: None,
+        desc: None,
+        priority: None,
+        state: None,
+        changes: Vec::new(),
+    };
+
+    let current_title = current.title.clone().unwrap_or_default();
+    if let Some(title) = &row.title
+        && *title != current_title
+    {
+        update.changes.push(FieldChange {
+            id: row.id,
+            field: "title",
+            from: current_title,
+            to: title.clone(),
+        });
+        update.title = Some(title.clone());
+    }
+
+    let current_desc = current.desc.clone().unwrap_or_default();
+    if let Some(desc) = &row.desc
+        && *desc != current_desc
+    {
+        update.changes.push(FieldChange {
+            id: row.id,
+            field: "desc",
+            from: current_desc,
+            to: desc.clone(),
+        });
+        update.desc = Some(desc.clone());
+    }
+
+    if let Some(priority) = row.priority
+        && priority != current.priority
+    {
+        update.changes.push(FieldChange {
+            id: row.id,
+            field: "priority",
+            from: current.priority.to_string(),
+            to: priority.to_string(),
+        });
+        update.priority = Some(priority);
+    }
+
+    if let Some(state) = row.state
+        && state.as_str() != current.state
+    {
+        update.changes.push(FieldChange {
+            id: row.id,
+            field: "state",
+            from: current.state.clone(),
+            to: state.as_str().to_string(),
+        });
+        update.state = Some(state);
+    }
+
+    update
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: i64, title: &str, priority: i64) -> DumpTask {
+        DumpTask {
+            id,
+            title: Some(title.to_string()),
+            desc: None,
+            priority,
+            state: "ready".to_string(),
+            key: None,
+            verify: None,
+            attempts: 0,
+            not_before: None,
+            due_at: None,
+            criteria: Vec::new(),
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    #[test]
+    fn render_then_parse_round_trips_without_changes() {
+        let tasks = [task(1, "Parser, v2", 1), task(2, "Docs", 3)];
+        let parents = HashMap::from([(2, vec![1])]);
+        let contents = render(&tasks, &parents).expect("render");
+        assert!(contents.starts_with("id,title,desc,priority,state,parents\n"));
+
+        let rows = parse(&contents).expect("parse");
+        assert_eq!(rows.len(), 2);
+        for (row, task) in rows.iter().zip(&tasks) {
+            assert!(diff(row, task).changes.is_empty());
+        }
+    }
+
+    #[test]
+    fn diff_only_reports_present_changed_columns() {
+        let rows = parse("id,priority,state\n1,4,closed\n2,3,ready\n").expect("parse");
+
+        let update = diff(&rows[0], &task(1, "Parser", 1));
+        let fields: Vec<&str> = update.changes.iter().map(|change| change.field).collect();
+        assert_eq!(fields, ["priority", "state"]);
+        assert_eq!(update.priority, Some(4));
+        assert!(update.title.is_none());
+
+        assert!(diff(&rows[1], &task(2, "Docs", 3)).changes.is_empty());
+    }
+
+    #[test]
+    fn parse_rejects_repeated_ids() {
+        let err = parse("id,priority\n1,4\n2,3\n1,2\n").expect_err("repeated id");
+        assert_eq!(err, "line 4: task 1 already appears on line 2");
+    }
+}
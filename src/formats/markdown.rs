@@ -0,0 +1,271 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::db::dump::{DUMP_VERSION, Dump, DumpDependency, DumpTask};
+use crate::db::tasks::TaskRow;
+
+const SECTIONS: [(&str, &str); 6] = [
+    ("ready", "Ready"),
+    ("in_progress", "In Progress"),
+    ("needs_human", "Needs Human"),
+    ("blocked", "Blocked"),
+    ("deferred", "Deferred"),
+    ("closed", "Closed"),
+];
+
+/// Parses a Markdown checklist into a dump with document-order ids. Nested
+/// items become children of the item they are nested under, checked items are
+/// closed, and indented non-item text below an item becomes its description.
+pub fn parse(contents: &str) -> Dump {
+    let mut tasks: Vec<DumpTask> = Vec::new();
+    let mut dependencies = Vec::new();
+    let mut stack: Vec<(usize, i64)> = Vec::new();
+
+    for line in contents.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let indent = indent_width(line);
+
+        if let Some((checked, title)) = parse_item(trimmed) {
+            while stack.last().is_some_and(|(depth, _)| *depth >= indent) {
+                stack.pop();
+            }
+            let id = tasks.len() as i64 + 1;
+            if let Some((_, parent_id)) = stack.last() {
+                dependencies.push(DumpDependency {
+                    parent_id: *parent_id,
+                    child_id: id,
+                });
+            }
+            tasks.push(DumpTask {
+                id,
+                title: Some(title.to_string()),
+                desc: None,
+                priority: 1,
+                state: if checked { "closed" } else { "ready" }.to_string(),
+                key: None,
+                verify: None,
+                attempts: 0,
+                not_before: None,
+                due_at: None,
+                criteria: Vec::new(),
+                created_at: 0,
+                updated_at: 0,
+            });
+            stack.push((indent, id));
+            continue;
+        }
+
+        match stack.last() {
+            Some((depth, id)) if indent > *depth => {
+                let task = &mut tasks[*id as usize - 1];
+                match &mut task.desc {
+                    Some(desc) => {
+                        desc.push('\n');
+                        desc.push_str(trimmed);
+                    }
+                    None => task.desc = Some(trimmed.to_string()),
+                }
+            }
+            _ => stack.clear(),
+        }
+    }
+
+    Dump {
+        version: DUMP_VERSION,
+        tasks,
+        dependencies,
+        notes: Vec::new(),
+    }
+}
+
+/// Renders tasks as a checklist grouped by the state of each top-level item.
+/// Every task appears once, nested under its lowest-id parent.
+pub fn render(tasks: &[TaskRow]) -> String {
+    let by_id: HashMap<i64, &TaskRow> = tasks.iter().map(|task| (task.id, task)).collect();
+    let mut ordered: Vec<&TaskRow> = tasks.iter().collect();
+    ordered.sort_by_key(|task| (task.priority, task.id));
+
+    let mut nested: HashMap<i64, Vec<i64>> = HashMap::new();
+    let mut roots = Vec::new();
+    for task in &ordered {
+        match task.parents.iter().find(|id| by_id.contains_key(id)) {
+            Some(parent_id) => nested.entry(*parent_id).or_default().push(task.id),
+            None => roots.push(task.id),
+        }
+    }
+
+    // Tasks caught in a dependency cycle are unreachable from any root;
+    // promote them so they still show up.
+    let mut reachable = HashSet::new();
+    for root in &roots {
+        mark_reachable(*root, &nested, &mut reachable);
+    }
+    for task in &ordered {
+        if !reachable.contains(&task.id) {
+            roots.push(task.id);
+            mark_reachable(task.id, &nested, &mut reachable);
+        }
+    }
+
+    let mut out = String::from("# Tasks\n");
+    let mut written = HashSet::new();
+    for (state, heading) in SECTIONS {
+        let section: Vec<i64> = roots
+            .iter()
+            .copied()
+            .filter(|id| by_id[id].state == state)
+            .collect();
+        if section.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("\n## {heading}\n\n"));
+        for id in section {
+            write_item(&mut out, id, 0, &by_id, &nested, &mut written);
+        }
+    }
+    out
+}
+
+fn write_item(
+    out: &mut String,
+    id: i64,
+    depth: usize,
+    by_id: &HashMap<i64, &TaskRow>,
+    nested: &HashMap<i64, Vec<i64>>,
+    written: &mut HashSet<i64>,
+) {
+    if !written.insert(id) {
+        return;
+    }
+
+    let task = by_id[&id];
+    let indent = "  ".repeat(depth);
+    let mark = if task.state == "closed" { 'x' } else { ' ' };
+    out.push_str(&format!(
+        "{indent}- [{mark}] {}\n",
+        task.title.as_deref().unwrap_or("")
+    ));
+    if let Some(desc) = task.desc.as_deref() {
+        for line in desc.lines().filter(|line| !line.trim().is_empty()) {
+            out.push_str(&format!("{indent}  {}\n", line.trim()));
+        }
+    }
+
+    if let Some(children) = nested.get(&id) {
+        for child in children {
+            write_item(out, *child, depth + 1, by_id, nested, written);
+        }
+    }
+}
+
+fn mark_reachable(id: i64, nested: &HashMap<i64, Vec<i64>>, reachable: &mut HashSet<i64>) {
+    if !reachable.insert(id) {
+        return;
+    }
+    if let Some(children) = nested.get(&id) {
+        for child in children {
+            mark_reachable(*child, nested, reachable);
+        }
+    }
+}
+
+fn parse_item(trimmed: &str) -> Option<(bool, &str)> {
+    let rest = trimmed
+        .strip_prefix("- ")
+        .or_else(|| trimmed.strip_prefix("* "))
+        .or_else(|| trimmed.strip_prefix("+ "))?;
+    let (checked, title) = if let Some(title) = rest.strip_prefix("[ ]") {
+        (false, title)
+    } else if let Some(title) = rest
+        .strip_prefix("[x]")
+        .or_else(|| rest.strip_prefix("[X]"))
+    {
+        (true, title)
+    } else {
+        return None;
+    };
+    Some((checked, title.trim()))
+}
+
+fn indent_width(line: &str) -> usize {
+    line.chars()
+        .take_while(|ch| ch.is_whitespace())
+        .map(|ch| if ch == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(id: i64, title: &str, state: &str, parents: &[i64]) -> TaskRow {
+        TaskRow {
+            id,
+            title: Some(title.to_string()),
+            desc: None,
+            priority: 1,
+            state: state.to_string(),
+            key: None,
+            verify: None,
+            attempts: 0,
+            not_before: None,
+            due_at: None,
+            effective_priority: None,
+            created_at: 0,
+            updated_at: 0,
+            parents: parents.to_vec(),
+            children: Vec::new(),
+            criteria: Vec::new(),
+            blocked_by: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn parse_nests_items_and_closes_checked() {
+        let dump = parse(
+            "# Plan\n\
+             - [ ] Build parser\n\
+             \x20 Parse the grammar.\n\
+             \x20 - [x] Lexer\n\
+             \x20 - [ ] Grammar\n\
+             \x20   - [ ] Precedence\n\
+             - [X] Setup\n",
+        );
+
+        let titles: Vec<&str> = dump
+            .tasks
+            .iter()
+            .map(|task| task.title.as_deref().unwrap_or(""))
+            .collect();
+        assert_eq!(
+            titles,
+            ["Build parser", "Lexer", "Grammar", "Precedence", "Setup"]
+        );
+        assert_eq!(dump.tasks[0].desc.as_deref(), Some("Parse the grammar."));
+        assert_eq!(dump.tasks[1].state, "closed");
+        assert_eq!(dump.tasks[4].state, "closed");
+
+        let edges: Vec<(i64, i64)> = dump
+            .dependencies
+            .iter()
+            .map(|dep| (dep.parent_id, dep.child_id))
+            .collect();
+        assert_eq!(edges, [(1, 2), (1, 3), (3, 4)]);
+    }
+
+    #[test]
+    fn render_groups_roots_by_state_and_nests_children() {
+        let tasks = [
+            row(1, "Build parser", "ready", &[]),
+            row(2, "Lexer", "blocked", &[1]),
+            row(3, "Setup", "closed", &[]),
+        ];
+
+        assert_eq!(
+            render(&tasks),
+            "# Tasks\n\n## Ready\n\n- [ ] Build parser\n  - [ ] Lexer\n\n## Closed\n\n- [x] Setup\n"
+        );
+    }
+}
@@ -0,0 +1,3 @@
+pub mod beads;
+pub mod csv;
+pub mod markdown;
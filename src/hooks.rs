@@ -0,0 +1,143 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use sea_orm_migration::sea_orm::{DatabaseConnection, DbErr};
+
+use crate::db::tasks;
+use crate::db::watch::{self, EventKind, Snapshot, TaskEvent};
+use crate::schema;
+
+/// Hook scripts, looked up in `.pearls/hooks/` next to the database.
+const HOOKS: [(EventKind, &str); 3] = [
+    (EventKind::Claimed, "on-claim"),
+    (EventKind::Closed, "on-close"),
+    (EventKind::Unblocked, "on-unblock"),
+];
+
+/// Scripts run after a write claims, closes, or unblocks a task. Each gets
+/// the task as JSON on stdin plus `PEARLS_*` variables describing it.
+#[derive(Debug)]
+pub struct Hooks {
+    dir: PathBuf,
+    db_path: PathBuf,
+    enabled: bool,
+    quiet: bool,
+}
+
+impl Hooks {
+    pub fn for_database(db_path: &Path) -> Self {
+        let dir = db_path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join(".pearls")
+            .join("hooks");
+        let enabled = HOOKS.iter().any(|(_, name)| dir.join(name).is_file());
+        Self {
+            dir,
+            db_path: db_path.to_path_buf(),
+            enabled,
+            quiet: false,
+        }
+    }
+
+    /// Discards hook output instead of passing it to stderr, for callers
+    /// that own the terminal.
+    pub fn quiet(mut self) -> Self {
+        self.quiet = true;
+        self
+    }
+
+    /// Loads `ids` and their children ahead of a write, so [`Hooks::events`]
+    /// can tell what the write changed. Empty when no hooks are installed.
+    pub async fn track(&self, conn: &DatabaseConnection, ids: &[i64]) -> Result<Snapshot, DbErr> {
+        let mut snapshot = Snapshot::new();
+        if !self.enabled {
+            return Ok(snapshot);
+        }
+        for id in ids {
+            let task = tasks::get_task_by_id(conn, *id).await?;
+            for child in &task.children {
+                if !snapshot.contains_key(child) {
+                    snapshot.insert(*child, tasks::get_task_by_id(conn, *child).await?);
+                }
+            }
+            snapshot.insert(*id, task);
+        }
+        Ok(snapshot)
+    }
+
+    /// Events for the tasks in `before`, as they are now.
+    pub async fn events(
+        &self,
+        conn: &DatabaseConnection,
+        before: &Snapshot,
+    ) -> Result<Vec<TaskEvent>, DbErr> {
+        let mut after = Snapshot::new();
+        for id in before.keys() {
+            after.insert(*id, tasks::get_task_by_id(conn, *id).await?);
+        }
+        Ok(watch::diff(before, &after))
+    }
+
+    /// Runs the hook for each event that has one, in order, and returns a
+    /// message per hook that could not run or exited unsuccessfully. Call
+    /// this after releasing the database lock so hooks can run `pearls`.
+    pub fn run(&self, events: &[TaskEvent]) -> Vec<String> {
+        let mut failures = Vec::new();
+        if !self.enabled {
+            return failures;
+        }
+        for event in events {
+            let Some((_, name)) = HOOKS.iter().find(|(kind, _)| *kind == event.event) else {
+                continue;
+            };
+            let path = self.dir.join(name);
+            if !path.is_file() {
+                continue;
+            }
+            if let Err(err) = self.run_one(&path, name, event) {
+                failures.push(format!("hook {name} failed for #{}: {err}", event.task.id));
+            }
+        }
+        failures
+    }
+
+    fn run_one(&self, path: &Path, name: &str, event: &TaskEvent) -> std::io::Result<()> {
+        let payload = serde_json::to_value(&event.task)
+            .map(schema::versioned)
+            .map_err(std::io::Error::other)?;
+        let (stdout, stderr) = if self.quiet {
+            (Stdio::null(), Stdio::null())
+        } else {
+            (Stdio::from(std::io::stderr()), Stdio::inherit())
+        };
+
+        let mut command = Command::new(path);
+        command
+            .env("PEARLS_HOOK", name)
+            .env("PEARLS_EVENT", event.event.as_str())
+            .env("PEARLS_TASK_ID", event.task.id.to_string())
+            .env("PEARLS_TASK_STATE", &event.task.state)
+            .env("PEARLS_DB", &self.db_path)
+            .stdin(Stdio::piped())
+            .stdout(stdout)
+            .stderr(stderr);
+        match &event.task.key {
+            Some(key) => command.env("PEARLS_TASK_KEY", key),
+            None => command.env_remove("PEARLS_TASK_KEY"),
+        };
+
+        let mut child = command.spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // A hook that ignores stdin may exit before reading it.
+            let _ = writeln!(stdin, "{payload}");
+        }
+        let status = child.wait()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(std::io::Error::other(format!("exited with {status}")))
+        }
+    }
+}
@@ -1,14 +1,62 @@
 mod cli;
+mod complete;
 mod db;
+mod formats;
+mod hooks;
+mod output;
+mod schema;
+mod tui;
+mod verify;
 
-use clap::Parser;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use clap::{CommandFactory, Parser};
+use clap_complete::CompleteEnv;
 use filelock::FileLock;
+use sea_orm_migration::sea_orm::{DatabaseConnection, DbErr, TransactionTrait};
 use serde::Serialize;
-use serde_json::json;
+
+/// Set once a command reports an error, so the process exits with status 1.
+static FAILED: AtomicBool = AtomicBool::new(false);
+
+/// `eprintln!` for errors: the message goes to stderr and the command fails.
+macro_rules! fail {
+    ($($arg:tt)*) => {{
+        FAILED.store(true, Ordering::Relaxed);
+        eprintln!($($arg)*)
+    }};
+}
+
+fn main() {
+    // Answers shell completion requests and exits; task id candidates start
+    // their own runtime, so this must run outside of `run`.
+    CompleteEnv::with_factory(<cli::Cli as CommandFactory>::command)
+        .var(complete::COMPLETE_VAR)
+        .complete();
+    run();
+    if FAILED.load(Ordering::Relaxed) {
+        std::process::exit(1);
+    }
+}
 
 #[tokio::main(flavor = "current_thread")]
-async fn main() {
+async fn run() {
     let cli = cli::Cli::parse();
+    match cli.command() {
+        cli::Commands::Schema { command } => {
+            print_schemas(command.as_deref());
+            return;
+        }
+        cli::Commands::Completions { shell } => {
+            if let Err(err) = complete::write_registration(*shell) {
+                fail!("failed to write completions: {err}");
+            }
+            return;
+        }
+        _ => {}
+    }
     let db_path = cli.db_path();
     let conn = db::conn::connect(&db_path)
         .await
@@ -18,55 +66,221 @@ async fn main() {
     let lock_path = db_path.with_extension("lock");
     let lock_path = lock_path.to_string_lossy().to_string();
     let mut lock = FileLock::new(&lock_path);
+    let hooks = hooks::Hooks::for_database(&db_path);
 
     match command {
         cli::Commands::Tasks(tasks) => match &tasks.command {
             cli::TaskSubcommand::List {
                 state,
                 offset,
+                cursor,
+                bare_array,
+                json_lines,
+                limit,
+                sort,
+                desc,
+                priority_min,
+                priority_max,
+                parent,
+                descendant_of,
+                has_children,
+                overdue,
+                title_contains,
+                count,
+                wide,
+                output,
+            } => {
+                let mut filter = db::tasks::TaskFilter {
+                    states: state.clone(),
+                    priority_min: *priority_min,
+                    priority_max: *priority_max,
+                    parent: None,
+                    descendant_of: None,
+                    has_children: *has_children,
+                    title_contains: title_contains.clone(),
+                    overdue: *overdue,
+                };
+                for (task, slot) in [
+                    (parent, &mut filter.parent),
+                    (descendant_of, &mut filter.descendant_of),
+                ] {
+                    if let Some(task) = task {
+                        match db::tasks::resolve_ref(&conn, task).await {
+                            Ok(id) => *slot = Some(id),
+                            Err(err) => {
+                                fail!("failed to resolve {task}: {err}");
+                                return;
+                            }
+                        }
+                    }
+                }
+
+                if *count {
+                    match db::tasks::count_tasks_by_state(&conn, &filter).await {
+                        Ok(counts) => {
+                            let total: u64 = counts.values().sum();
+                            if json_output {
+                                print_json(&schema::StateCounts {
+                                    states: counts,
+                                    total,
+                                });
+                            } else {
+                                for (state, count) in &counts {
+                                    println!("{state}: {count}");
+                                }
+                                println!("total: {total}");
+                            }
+                        }
+                        Err(err) => {
+                            fail!("failed to count tasks: {err}");
+                        }
+                    }
+                    return;
+                }
+
+                let page = db::tasks::PageRequest {
+                    sort: *sort,
+                    descending: *desc,
+                    offset: *offset,
+                    limit: *limit,
+                    cursor: cursor.clone(),
+                };
+                if *json_lines {
+                    let template = output.format.clone();
+                    let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+                    let result = db::tasks::for_each_task(&conn, &filter, &page, |row| {
+                        let line = match &template {
+                            Some(template) => template.render(&row),
+                            None => project(&row, &output.fields).to_string(),
+                        };
+                        // A closed pipe (e.g. `| head`) just ends the stream.
+                        writeln!(out, "{line}").is_ok()
+                    })
+                    .await;
+                    let _ = out.flush();
+                    if let Err(err) = result {
+                        fail!("failed to list tasks: {err}");
+                    }
+                    return;
+                }
+                match db::tasks::list_tasks_paginated(&conn, &filter, &page).await {
+                    Ok(page) => {
+                        if let Some(template) = output.template(json_output) {
+                            for row in &page.items {
+                                println!("{}", template.render(row));
+                            }
+                        } else if json_output && *bare_array {
+                            print_json(&project(&page.items, &output.fields));
+                        } else if json_output {
+                            let mut payload = serde_json::to_value(&page).unwrap_or_default();
+                            payload["items"] =
+                                output::project(payload["items"].take(), &output.fields);
+                            print_json(&payload);
+                        } else {
+                            let style = output::Style::detect(cli.no_color());
+                            print!("{}", output::render_table(&page.items, style, *wide));
+                        }
+                    }
+                    Err(err) => {
+                        fail!("failed to list tasks: {err}");
+                    }
+                }
+            }
+            cli::TaskSubcommand::Search {
+                query,
+                state,
                 limit,
             } => {
-                match db::tasks::list_tasks_paginated(&conn, state, *offset, *limit).await {
-                    Ok(rows) => {
+                let highlight = if output::Style::detect(cli.no_color()).color {
+                    ("\x1b[1m", "\x1b[0m")
+                } else {
+                    ("**", "**")
+                };
+                match db::tasks::search_tasks(&conn, query, state, *limit, highlight).await {
+                    Ok(hits) => {
                         if json_output {
+                            let rows: Vec<&db::tasks::TaskRow> =
+                                hits.iter().map(|hit| &hit.task).collect();
                             print_json(&rows);
                         } else {
-                            for row in rows {
-                                println!("{}", row.display_line());
+                            for hit in hits {
+                                println!("{}", hit.task.display_line());
+                                println!("    {}", hit.snippet);
                             }
                         }
                     }
                     Err(err) => {
-                        eprintln!("failed to list tasks: {err}");
+                        fail!("failed to search tasks: {err}");
                     }
                 }
             }
-            cli::TaskSubcommand::ClaimNext => {
-                let _guard = match lock.lock() {
-                    Ok(guard) => guard,
+            cli::TaskSubcommand::Show { id, output } => {
+                let task = match db::tasks::resolve_ref(&conn, id).await {
+                    Ok(id) => db::tasks::get_task_detail(&conn, id).await,
+                    Err(err) => Err(err),
+                };
+                match task {
+                    Ok(task) => {
+                        if let Some(template) = output.template(json_output) {
+                            println!("{}", template.render(&task));
+                        } else if json_output {
+                            print_json(&project(&task, &output.fields));
+                        } else {
+                            println!("{}", task.display_detail());
+                        }
+                    }
                     Err(err) => {
-                        eprintln!("{err}");
-                        return;
+                        fail!("failed to show task: {err}");
                     }
+                }
+            }
+            cli::TaskSubcommand::ClaimNext {
+                wait,
+                timeout,
+                schedule,
+                output,
+            } => {
+                let claimed = if *wait {
+                    claim_next_waiting(&conn, &db_path, &mut lock, *schedule, *timeout).await
+                } else {
+                    let _guard = match lock.lock() {
+                        Ok(guard) => guard,
+                        Err(err) => {
+                            fail!("{err}");
+                            return;
+                        }
+                    };
+                    db::tasks::claim_next(&conn, *schedule).await
                 };
 
-                match db::tasks::claim_next(&conn).await {
+                match claimed {
                     Ok(Some(task)) => {
-                        if json_output {
-                            print_json(&task);
+                        if let Some(template) = output.template(json_output) {
+                            println!("{}", template.render(&task));
+                        } else if json_output {
+                            print_json(&project(&task, &output.fields));
                         } else {
-                            println!("{}", task.display_line());
+                            println!("{}", task.display_detail());
                         }
+                        run_hooks(
+                            &hooks,
+                            Ok(vec![db::watch::TaskEvent {
+                                event: db::watch::EventKind::Claimed,
+                                task,
+                            }]),
+                        );
                     }
                     Ok(None) => {
                         if json_output {
-                            print_json(&json!({ "status": "no_ready_tasks" }));
+                            print_json(&schema::Status {
+                                status: "no_ready_tasks",
+                            });
                         } else {
                             println!("no ready tasks");
                         }
                     }
                     Err(err) => {
-                        eprintln!("failed to claim next task: {err}");
+                        fail!("failed to claim next task: {err}");
                     }
                 }
             }
@@ -76,50 +290,146 @@ async fn main() {
                 parent_of,
                 child_of,
                 priority,
+                no_duplicates,
+                idempotent,
+                key,
+                verify,
+                not_before,
+                due,
             } => {
                 let _guard = match lock.lock() {
                     Ok(guard) => guard,
                     Err(err) => {
-                        eprintln!("{err}");
+                        fail!("{err}");
                         return;
                     }
                 };
 
-                let task = match db::tasks::add_task(&conn, title, description, *priority).await {
+                if let Some(key) = key {
+                    match db::tasks::get_task_by_key(&conn, key).await {
+                        Ok(Some(existing)) => {
+                            if json_output {
+                                print_json(&existing);
+                            } else {
+                                println!("task already exists as #{}", existing.id);
+                            }
+                            return;
+                        }
+                        Ok(None) => {}
+                        Err(err) => {
+                            fail!("failed to look up key {key:?}: {err}");
+                            return;
+                        }
+                    }
+                }
+
+                let mut dep_targets = Vec::new();
+                for other in [parent_of, child_of] {
+                    match other {
+                        Some(other) => match db::tasks::resolve_ref(&conn, other).await {
+                            Ok(id) => dep_targets.push(Some(id)),
+                            Err(err) => {
+                                fail!("failed to resolve {other}: {err}");
+                                return;
+                            }
+                        },
+                        None => dep_targets.push(None),
+                    }
+                }
+                let (parent_of, child_of) = (dep_targets[0], dep_targets[1]);
+
+                let duplicates =
+                    match db::duplicates::find_duplicates(&conn, title, description).await {
+                        Ok(duplicates) => duplicates,
+                        Err(err) => {
+                            fail!("failed to check for duplicates: {err}");
+                            return;
+                        }
+                    };
+                if let Some(existing) = duplicates.first() {
+                    if *idempotent {
+                        if json_output {
+                            print_json(&existing.task);
+                        } else {
+                            println!("task already exists as #{}", existing.task.id);
+                        }
+                        return;
+                    }
+                    if *no_duplicates {
+                        fail!("refusing to add task; similar open tasks exist:");
+                        for duplicate in &duplicates {
+                            fail!("  {}", duplicate.task.display_line());
+                        }
+                        return;
+                    }
+                    for duplicate in &duplicates {
+                        eprintln!(
+                            "warning: possible duplicate of #{} ({:.0}% similar): {}",
+                            duplicate.task.id,
+                            duplicate.similarity * 100.0,
+                            duplicate.task.title.as_deref().unwrap_or("")
+                        );
+                    }
+                }
+
+                let task = match db::tasks::add_task(
+                    &conn,
+                    title,
+                    description,
+                    *priority,
+                    key.as_deref(),
+                )
+                .await
+                {
                     Ok(task) => task,
                     Err(err) => {
-                        eprintln!("failed to add task: {err}");
+                        fail!("failed to add task: {err}");
                         return;
                     }
                 };
 
                 let mut dep_errors = Vec::new();
-                if let Some(other) = *parent_of
-                    && let Err(err) =
-                        db::tasks::add_dependency(&conn, task.id, other as i64).await
+                if let Some(other) = parent_of
+                    && let Err(err) = db::tasks::add_dependency(&conn, task.id, other).await
                 {
                     dep_errors.push(err);
                 }
-                if let Some(other) = *child_of
-                    && let Err(err) =
-                        db::tasks::add_dependency(&conn, other as i64, task.id).await
+                if let Some(other) = child_of
+                    && let Err(err) = db::tasks::add_dependency(&conn, other, task.id).await
                 {
                     dep_errors.push(err);
                 }
 
                 let has_deps = parent_of.is_some() || child_of.is_some();
                 if !dep_errors.is_empty() {
-                    eprintln!("task added but failed to update dependencies");
+                    fail!("task added but failed to update dependencies");
                     for err in dep_errors {
-                        eprintln!("  - {err}");
+                        fail!("  - {err}");
                     }
                 }
+                if let Some(verify) = verify
+                    && let Err(err) = db::tasks::set_verify(&conn, task.id, Some(verify)).await
+                {
+                    fail!("task added but failed to set verify command: {err}");
+                }
+                let not_before = not_before.and_then(|at| at.resolve());
+                if not_before.is_some()
+                    && let Err(err) = db::tasks::set_not_before(&conn, task.id, not_before).await
+                {
+                    fail!("task added but failed to set start time: {err}");
+                }
+                let due_at = due.and_then(|at| at.resolve());
+                if due_at.is_some()
+                    && let Err(err) = db::tasks::set_due_at(&conn, task.id, due_at).await
+                {
+                    fail!("task added but failed to set due date: {err}");
+                }
 
                 if json_output {
-                    if has_deps {
+                    if has_deps || verify.is_some() || not_before.is_some() || due_at.is_some() {
                         match db::tasks::get_task_by_id(&conn, task.id).await {
                             Ok(updated) => print_json(&updated),
-                            Err(err) => eprintln!("task added but failed to load: {err}"),
+                            Err(err) => fail!("task added but failed to load: {err}"),
                         }
                     } else {
                         print_json(&task);
@@ -134,33 +444,88 @@ async fn main() {
                 desc,
                 priority,
                 state,
+                verify,
+                not_before,
+                due,
             } => {
-                let _guard = match lock.lock() {
+                let guard = match lock.lock() {
                     Ok(guard) => guard,
                     Err(err) => {
-                        eprintln!("{err}");
+                        fail!("{err}");
                         return;
                     }
                 };
 
-                match db::tasks::update_metadata(
-                    &conn,
-                    *id as i64,
-                    title.as_deref(),
-                    desc.as_deref(),
-                    *priority,
-                    *state,
-                )
-                .await
-                {
+                let id = match db::tasks::resolve_ref(&conn, id).await {
+                    Ok(id) => id,
+                    Err(err) => {
+                        fail!("failed to resolve {id}: {err}");
+                        return;
+                    }
+                };
+                if matches!(state, Some(cli::TaskState::Closed)) {
+                    match db::tasks::get_task_by_id(&conn, id).await {
+                        Ok(task) if task.verify.is_some() => {
+                            fail!(
+                                "task #{id} has a verify command; close it with `pearls tasks close --id {id}`"
+                            );
+                            return;
+                        }
+                        Ok(task) if task.unchecked_criteria() > 0 => {
+                            fail!(
+                                "task #{id} has unchecked acceptance criteria; close it with `pearls tasks close --id {id}`"
+                            );
+                            return;
+                        }
+                        Ok(_) => {}
+                        Err(err) => {
+                            fail!("failed to load task: {err}");
+                            return;
+                        }
+                    }
+                }
+                let before = match hooks.track(&conn, &[id]).await {
+                    Ok(before) => before,
+                    Err(err) => {
+                        fail!("failed to load task: {err}");
+                        return;
+                    }
+                };
+
+                let updated = async {
+                    let mut changes = db::tasks::update_metadata(
+                        &conn,
+                        id,
+                        title.as_deref(),
+                        desc.as_deref(),
+                        *priority,
+                        *state,
+                    )
+                    .await?;
+                    if let Some(verify) = verify {
+                        let verify = Some(verify.as_str()).filter(|verify| !verify.is_empty());
+                        changes += db::tasks::set_verify(&conn, id, verify).await?;
+                    }
+                    if let Some(not_before) = not_before {
+                        changes +=
+                            db::tasks::set_not_before(&conn, id, not_before.resolve()).await?;
+                    }
+                    if let Some(due) = due {
+                        changes += db::tasks::set_due_at(&conn, id, due.resolve()).await?;
+                    }
+                    Ok::<_, DbErr>(changes)
+                };
+                match updated.await {
                     Ok(0) => {
                         if json_output {
-                            print_json(&json!({ "status": "no_changes" }));
+                            print_json(&schema::Status {
+                                status: "no_changes",
+                            });
                         } else {
                             eprintln!("no fields to update");
                         }
                     }
-                    Ok(_) => match db::tasks::get_task_by_id(&conn, *id as i64).await {
+                    Ok(_) => match db::tasks::get_task_by_id(&conn, id).await {
                         Ok(task) => {
                             if json_output {
                                 print_json(&task);
@@ -169,12 +534,324 @@ async fn main() {
                             }
                         }
                         Err(err) => {
-                            eprintln!("task updated but failed to load: {err}");
+                            fail!("task updated but failed to load: {err}");
+                        }
+                    },
+                    Err(err) => {
+                        fail!("failed to update task: {err}");
+                    }
+                }
+                let events = hooks.events(&conn, &before).await;
+                drop(guard);
+                run_hooks(&hooks, events);
+            }
+            cli::TaskSubcommand::Close {
+                id,
+                skip_verify,
+                force,
+            } => {
+                let task = match db::tasks::resolve_ref(&conn, id).await {
+                    Ok(id) => db::tasks::get_task_by_id(&conn, id).await,
+                    Err(err) => Err(err),
+                };
+                let task = match task {
+                    Ok(task) => task,
+                    Err(err) => {
+                        fail!("failed to load task: {err}");
+                        return;
+                    }
+                };
+                let id = task.id;
+                if refuse_close(&task, *force) {
+                    return;
+                }
+
+                // The verify command may take a while, so it runs before the
+                // lock is taken.
+                let mut failure = None;
+                if let Some(command) = task.verify.as_deref().filter(|_| !*skip_verify) {
+                    if !json_output {
+                        eprintln!("verifying #{id}: {command}");
+                    }
+                    match verify::run(command) {
+                        Ok(outcome) if outcome.status.success() => {}
+                        Ok(outcome) => failure = Some((command, outcome)),
+                        Err(err) => {
+                            fail!("failed to run verify command for #{id}: {err}");
+                            return;
+                        }
+                    }
+                }
+
+                let guard = match lock.lock() {
+                    Ok(guard) => guard,
+                    Err(err) => {
+                        fail!("{err}");
+                        return;
+                    }
+                };
+
+                // Someone may have closed the task, added criteria, or
+                // changed the verify command while it ran.
+                let current = match db::tasks::get_task_by_id(&conn, id).await {
+                    Ok(current) => current,
+                    Err(err) => {
+                        fail!("failed to load task: {err}");
+                        return;
+                    }
+                };
+                if refuse_close(&current, *force) {
+                    return;
+                }
+                if current.verify != task.verify && !*skip_verify {
+                    fail!(
+                        "the verify command of #{id} changed while it ran; run `pearls tasks close --id {id}` again"
+                    );
+                    return;
+                }
+
+                if let Some((command, outcome)) = failure {
+                    FAILED.store(true, Ordering::Relaxed);
+                    let body = format!(
+                        "verify failed ({}): {command}\n{}",
+                        outcome.status, outcome.output
+                    );
+                    match db::notes::add_note(&conn, id, &body, None).await {
+                        Ok(note) => {
+                            if json_output {
+                                print_json(&schema::VerifyFailed {
+                                    status: "verify_failed",
+                                    exit_code: outcome.status.code(),
+                                    note,
+                                });
+                            } else {
+                                eprint!("{}", outcome.output);
+                                fail!(
+                                    "refusing to close #{id}: verify {} (output saved as note {})",
+                                    outcome.status, note.id
+                                );
+                            }
+                        }
+                        Err(err) => fail!("verify failed but the note was not saved: {err}"),
+                    }
+                    return;
+                }
+
+                let before = match hooks.track(&conn, &[id]).await {
+                    Ok(before) => before,
+                    Err(err) => {
+                        fail!("failed to load task: {err}");
+                        return;
+                    }
+                };
+                let closed = db::tasks::update_metadata(
+                    &conn,
+                    id,
+                    None,
+                    None,
+                    None,
+                    Some(cli::TaskState::Closed),
+                )
+                .await;
+                match closed {
+                    Ok(_) => match db::tasks::get_task_by_id(&conn, id).await {
+                        Ok(task) => {
+                            if json_output {
+                                print_json(&task);
+                            } else {
+                                println!("closed task #{id}");
+                            }
+                        }
+                        Err(err) => fail!("task closed but failed to load: {err}"),
+                    },
+                    Err(err) => fail!("failed to close task: {err}"),
+                }
+                let events = hooks.events(&conn, &before).await;
+                drop(guard);
+                run_hooks(&hooks, events);
+            }
+            cli::TaskSubcommand::Snooze { id, duration } => {
+                let guard = match lock.lock() {
+                    Ok(guard) => guard,
+                    Err(err) => {
+                        fail!("{err}");
+                        return;
+                    }
+                };
+
+                let id = match db::tasks::resolve_ref(&conn, id).await {
+                    Ok(id) => id,
+                    Err(err) => {
+                        fail!("failed to resolve {id}: {err}");
+                        return;
+                    }
+                };
+                let before = match hooks.track(&conn, &[id]).await {
+                    Ok(before) => before,
+                    Err(err) => {
+                        fail!("failed to load task: {err}");
+                        return;
+                    }
+                };
+                let until = cli::TimeArg::In(*duration).resolve();
+                match db::tasks::set_not_before(&conn, id, until).await {
+                    Ok(0) => fail!("task {id} not found"),
+                    Ok(_) => match db::tasks::get_task_by_id(&conn, id).await {
+                        Ok(task) => {
+                            if json_output {
+                                print_json(&task);
+                            } else {
+                                println!("snoozed task #{id} until {}", until.unwrap_or_default());
+                            }
                         }
+                        Err(err) => fail!("task snoozed but failed to load: {err}"),
                     },
+                    Err(err) => fail!("failed to snooze task: {err}"),
+                }
+                let events = hooks.events(&conn, &before).await;
+                drop(guard);
+                run_hooks(&hooks, events);
+            }
+            cli::TaskSubcommand::Fail {
+                id,
+                reason,
+                max_attempts,
+                backoff,
+            } => {
+                let _guard = match lock.lock() {
+                    Ok(guard) => guard,
+                    Err(err) => {
+                        fail!("{err}");
+                        return;
+                    }
+                };
+
+                let id = match db::tasks::resolve_ref(&conn, id).await {
+                    Ok(id) => id,
+                    Err(err) => {
+                        fail!("failed to resolve {id}: {err}");
+                        return;
+                    }
+                };
+                let outcome =
+                    db::tasks::record_failure(&conn, id, reason, *max_attempts, *backoff).await;
+                let outcome = match outcome {
+                    Ok(outcome) => outcome,
                     Err(err) => {
-                        eprintln!("failed to update task: {err}");
+                        fail!("failed to record failure: {err}");
+                        return;
+                    }
+                };
+                match db::tasks::get_task_by_id(&conn, id).await {
+                    Ok(task) => {
+                        if json_output {
+                            print_json(&task);
+                        } else if outcome == db::tasks::FailureOutcome::NeedsHuman {
+                            println!(
+                                "task #{id} needs a human after {} failed attempts",
+                                task.attempts
+                            );
+                        } else if let Some(not_before) = task.not_before {
+                            println!(
+                                "task #{id} failed (attempt {} of {max_attempts}); claimable again at {not_before}",
+                                task.attempts
+                            );
+                        } else {
+                            println!(
+                                "task #{id} failed (attempt {} of {max_attempts}); back in the queue",
+                                task.attempts
+                            );
+                        }
+                    }
+                    Err(err) => fail!("failure recorded but failed to load task: {err}"),
+                }
+            }
+            cli::TaskSubcommand::Criteria { command } => {
+                let guard = match lock.lock() {
+                    Ok(guard) => guard,
+                    Err(err) => {
+                        fail!("{err}");
+                        return;
+                    }
+                };
+
+                let task = match command {
+                    cli::CriteriaCommand::Add { id, .. }
+                    | cli::CriteriaCommand::Check { id, .. } => id,
+                };
+                let id = match db::tasks::resolve_ref(&conn, task).await {
+                    Ok(id) => id,
+                    Err(err) => {
+                        fail!("failed to resolve {task}: {err}");
+                        return;
+                    }
+                };
+                let updated = match command {
+                    cli::CriteriaCommand::Add { text, .. } => {
+                        let criteria: Vec<db::criteria::CriterionRow> = text
+                            .iter()
+                            .map(|text| db::criteria::CriterionRow {
+                                text: text.clone(),
+                                checked: false,
+                            })
+                            .collect();
+                        match db::tasks::get_task_by_id(&conn, id).await {
+                            Ok(_) => db::criteria::add_criteria(&conn, id, &criteria).await,
+                            Err(err) => Err(err),
+                        }
+                    }
+                    cli::CriteriaCommand::Check {
+                        item,
+                        all,
+                        uncheck,
+                        ..
+                    } => {
+                        let items = match db::tasks::get_task_by_id(&conn, id).await {
+                            Ok(task) if *all => (1..=task.criteria.len()).collect(),
+                            Ok(_) => item.clone(),
+                            Err(err) => {
+                                fail!("failed to load task: {err}");
+                                return;
+                            }
+                        };
+                        db::criteria::set_checked(&conn, id, &items, !*uncheck).await
                     }
+                };
+                drop(guard);
+
+                match updated {
+                    Ok(()) => match db::tasks::get_task_by_id(&conn, id).await {
+                        Ok(task) => {
+                            if json_output {
+                                print_json(&task);
+                            } else {
+                                println!("{}", task.display_detail());
+                            }
+                        }
+                        Err(err) => fail!("criteria updated but failed to load task: {err}"),
+                    },
+                    Err(err) => fail!("failed to update criteria: {err}"),
+                }
+            }
+            cli::TaskSubcommand::Notes { id } => {
+                let notes = match db::tasks::resolve_ref(&conn, id).await {
+                    Ok(id) => db::notes::list_notes(&conn, id).await,
+                    Err(err) => Err(err),
+                };
+                match notes {
+                    Ok(notes) => {
+                        if json_output {
+                            print_json(&notes);
+                        } else if notes.is_empty() {
+                            println!("no notes");
+                        } else {
+                            for note in &notes {
+                                println!("note {} (created {})", note.id, note.created_at);
+                                println!("{}", note.body.trim_end());
+                            }
+                        }
+                    }
+                    Err(err) => fail!("failed to list notes: {err}"),
                 }
             }
             cli::TaskSubcommand::UpdateDependency {
@@ -182,26 +859,39 @@ async fn main() {
                 add_child,
                 remove_child,
             } => {
-                let _guard = match lock.lock() {
+                let guard = match lock.lock() {
                     Ok(guard) => guard,
                     Err(err) => {
-                        eprintln!("{err}");
+                        fail!("{err}");
                         return;
                     }
                 };
 
-                let add_child: Vec<i64> = add_child.iter().map(|v| *v as i64).collect();
-                let remove_child: Vec<i64> = remove_child.iter().map(|v| *v as i64).collect();
+                let resolved = async {
+                    Ok::<_, DbErr>((
+                        db::tasks::resolve_ref(&conn, id).await?,
+                        db::tasks::resolve_refs(&conn, add_child).await?,
+                        db::tasks::resolve_refs(&conn, remove_child).await?,
+                    ))
+                };
+                let (id, add_child, remove_child) = match resolved.await {
+                    Ok(resolved) => resolved,
+                    Err(err) => {
+                        fail!("failed to resolve task: {err}");
+                        return;
+                    }
+                };
+                let tracked: Vec<i64> = std::iter::once(id).chain(remove_child.clone()).collect();
+                let before = match hooks.track(&conn, &tracked).await {
+                    Ok(before) => before,
+                    Err(err) => {
+                        fail!("failed to load task: {err}");
+                        return;
+                    }
+                };
 
-                match db::tasks::update_dependency(
-                    &conn,
-                    *id as i64,
-                    &add_child,
-                    &remove_child,
-                )
-                .await
-                {
-                    Ok(()) => match db::tasks::get_task_by_id(&conn, *id as i64).await {
+                match db::tasks::update_dependency(&conn, id, &add_child, &remove_child).await {
+                    Ok(()) => match db::tasks::get_task_by_id(&conn, id).await {
                         Ok(task) => {
                             if json_output {
                                 print_json(&task);
@@ -210,19 +900,532 @@ async fn main() {
                             }
                         }
                         Err(err) => {
-                            eprintln!("dependencies updated but failed to load task: {err}");
+                            fail!("dependencies updated but failed to load task: {err}");
                         }
                     },
-                    Err(err) => eprintln!("failed to update dependencies: {err}"),
+                    Err(err) => fail!("failed to update dependencies: {err}"),
+                }
+                let events = hooks.events(&conn, &before).await;
+                drop(guard);
+                run_hooks(&hooks, events);
+            }
+        },
+        cli::Commands::Export { format } => match format {
+            cli::ExportFormat::Json => match db::dump::export(&conn).await {
+                Ok(dump) => print_json(&dump),
+                Err(err) => fail!("failed to export tasks: {err}"),
+            },
+            cli::ExportFormat::Markdown => match db::tasks::list_tasks(&conn, &[]).await {
+                Ok(tasks) => print!("{}", formats::markdown::render(&tasks)),
+                Err(err) => fail!("failed to export tasks: {err}"),
+            },
+            cli::ExportFormat::Csv => match db::dump::export(&conn).await {
+                Ok(dump) => {
+                    let mut parents: HashMap<i64, Vec<i64>> = HashMap::new();
+                    for dep in &dump.dependencies {
+                        parents.entry(dep.child_id).or_default().push(dep.parent_id);
+                    }
+                    match formats::csv::render(&dump.tasks, &parents) {
+                        Ok(contents) => print!("{contents}"),
+                        Err(err) => fail!("failed to render csv: {err}"),
+                    }
+                }
+                Err(err) => fail!("failed to export tasks: {err}"),
+            },
+        },
+        cli::Commands::Import {
+            format,
+            from,
+            path,
+            remap_ids,
+            update,
+        } => {
+            let _guard = match lock.lock() {
+                Ok(guard) => guard,
+                Err(err) => {
+                    fail!("{err}");
+                    return;
+                }
+            };
+
+            let Some(from) = from.as_ref().or(path.as_ref()) else {
+                fail!("no file to import from");
+                return;
+            };
+            let contents = match std::fs::read_to_string(from) {
+                Ok(contents) => contents,
+                Err(err) => {
+                    fail!("failed to read {}: {err}", from.display());
+                    return;
+                }
+            };
+
+            if *update && !matches!(format, cli::ImportFormat::Csv) {
+                fail!("--update is only supported with --format csv");
+                return;
+            }
+
+            let mut source_ids = Vec::new();
+            let mut warnings = Vec::new();
+            let (dump, remap_ids): (db::dump::Dump, bool) = match format {
+                cli::ImportFormat::Json => match serde_json::from_str(&contents) {
+                    Ok(dump) => (dump, *remap_ids),
+                    Err(err) => {
+                        fail!("failed to parse {}: {err}", from.display());
+                        return;
+                    }
+                },
+                cli::ImportFormat::Markdown => (formats::markdown::parse(&contents), true),
+                cli::ImportFormat::Beads => match formats::beads::parse(&contents) {
+                    Ok(import) => {
+                        source_ids = import.source_ids;
+                        warnings = import.warnings;
+                        (import.dump, true)
+                    }
+                    Err(err) => {
+                        fail!("failed to parse {}: {err}", from.display());
+                        return;
+                    }
+                },
+                cli::ImportFormat::Csv if *update => {
+                    apply_csv_updates(&conn, &contents, from, json_output).await;
+                    return;
+                }
+                cli::ImportFormat::Csv => {
+                    fail!("csv import only updates existing tasks; pass --update");
+                    return;
+                }
+            };
+
+            match db::dump::import(&conn, &dump, remap_ids).await {
+                Ok(mut summary) => {
+                    if !source_ids.is_empty() {
+                        summary.source_ids = source_ids
+                            .into_iter()
+                            .enumerate()
+                            .filter_map(|(index, source)| {
+                                summary
+                                    .id_map
+                                    .get(&(index as i64 + 1))
+                                    .map(|id| (source, *id))
+                            })
+                            .collect();
+                        summary.id_map.clear();
+                    }
+                    summary.warnings = warnings;
+
+                    if json_output {
+                        print_json(&summary);
+                    } else {
+                        println!(
+                            "imported {} tasks and {} dependencies",
+                            summary.tasks, summary.dependencies
+                        );
+                        // Markdown item numbers are not meaningful source ids.
+                        if matches!(format, cli::ImportFormat::Json) {
+                            for (old, new) in &summary.id_map {
+                                println!("  #{old} -> #{new}");
+                            }
+                        }
+                        for (source, new) in &summary.source_ids {
+                            println!("  {source} -> #{new}");
+                        }
+                        for warning in &summary.warnings {
+                            eprintln!("warning: {warning}");
+                        }
+                    }
+                }
+                Err(err) => fail!("failed to import tasks: {err}"),
+            }
+        }
+        cli::Commands::Tui => {
+            if let Err(err) = tui::run(&conn, &mut lock, hooks.quiet()).await {
+                fail!("tui failed: {err}");
+            }
+        }
+        cli::Commands::Watch { interval } => {
+            let watcher = match db::conn::connect_single(&db_path).await {
+                Ok(watcher) => watcher,
+                Err(err) => {
+                    fail!("failed to open db for watching: {err}");
+                    return;
+                }
+            };
+            if let Err(err) = watch(&watcher, *interval, json_output).await {
+                fail!("watch failed: {err}");
+            }
+        }
+        // Handled before the database is opened.
+        cli::Commands::Schema { .. } | cli::Commands::Completions { .. } => {}
+        cli::Commands::Sync { export, import } => {
+            let _guard = match lock.lock() {
+                Ok(guard) => guard,
+                Err(err) => {
+                    fail!("{err}");
+                    return;
+                }
+            };
+
+            let default_path = db_path.with_file_name("tasks.jsonl");
+            if let Some(path) = export {
+                let path = path.clone().unwrap_or(default_path);
+                let records = match db::sync::load_records(&conn).await {
+                    Ok(records) => records,
+                    Err(err) => {
+                        fail!("failed to load tasks: {err}");
+                        return;
+                    }
+                };
+                let contents = match db::sync::render(&records) {
+                    Ok(contents) => contents,
+                    Err(err) => {
+                        fail!("failed to render sync file: {err}");
+                        return;
+                    }
+                };
+                if let Err(err) = std::fs::write(&path, contents) {
+                    fail!("failed to write {}: {err}", path.display());
+                    return;
+                }
+                if let Err(err) = db::sync::mark_synced(&conn, &records).await {
+                    fail!("sync file written but failed to record sync state: {err}");
+                    return;
+                }
+                if json_output {
+                    print_json(&schema::SyncExport {
+                        path,
+                        tasks: records.len(),
+                    });
+                } else {
+                    println!("exported {} tasks to {}", records.len(), path.display());
+                }
+            } else if let Some(path) = import {
+                let path = path.clone().unwrap_or(default_path);
+                let contents = match std::fs::read_to_string(&path) {
+                    Ok(contents) => contents,
+                    Err(err) => {
+                        fail!("failed to read {}: {err}", path.display());
+                        return;
+                    }
+                };
+                let records = match db::sync::parse(&contents) {
+                    Ok(records) => records,
+                    Err(err) => {
+                        fail!("failed to parse {}: {err}", path.display());
+                        return;
+                    }
+                };
+                match db::sync::merge(&conn, &records).await {
+                    Ok(summary) => {
+                        if json_output {
+                            print_json(&summary);
+                        } else {
+                            println!(
+                                "added {}, updated {}, kept {} local, {} unchanged, {} conflicts",
+                                summary.added.len(),
+                                summary.updated.len(),
+                                summary.kept_local.len(),
+                                summary.unchanged,
+                                summary.conflicts.len()
+                            );
+                            for conflict in &summary.conflicts {
+                                eprintln!(
+                                    "conflict on #{}: edited both locally and in {}",
+                                    conflict.id,
+                                    path.display()
+                                );
+                            }
+                        }
+                    }
+                    Err(err) => fail!("failed to merge sync file: {err}"),
+                }
+            }
+        }
+    }
+}
+
+async fn apply_csv_updates(
+    conn: &DatabaseConnection,
+    contents: &str,
+    from: &std::path::Path,
+    json_output: bool,
+) {
+    let rows = match formats::csv::parse(contents) {
+        Ok(rows) => rows,
+        Err(err) => {
+            fail!("failed to parse {}: {err}", from.display());
+            return;
+        }
+    };
+    let current: HashMap<i64, db::dump::DumpTask> = match db::dump::export(conn).await {
+        Ok(dump) => dump.tasks.into_iter().map(|task| (task.id, task)).collect(),
+        Err(err) => {
+            fail!("failed to load tasks: {err}");
+            return;
+        }
+    };
+
+    let mut updates = Vec::new();
+    let mut missing = Vec::new();
+    let mut unchanged = 0;
+    let mut refused = Vec::new();
+    for row in &rows {
+        match current.get(&row.id) {
+            Some(task) => {
+                let update = formats::csv::diff(row, task);
+                if update.changes.is_empty() {
+                    unchanged += 1;
+                    continue;
                 }
+                if let Some(state) = update.state.filter(|state| state.is_derived()) {
+                    refused.push(format!(
+                        "line {}: `{}` is derived from parents and not-before times and can't be set",
+                        row.line,
+                        state.as_str()
+                    ));
+                }
+                // Closing goes through `tasks close` when there is something
+                // to check first, as with `update-metadata --state closed`.
+                if matches!(update.state, Some(cli::TaskState::Closed)) {
+                    if task.verify.is_some() {
+                        refused.push(format!(
+                            "line {}: task #{} has a verify command; close it with `pearls tasks close --id {}`",
+                            row.line, row.id, row.id
+                        ));
+                    } else if task.criteria.iter().any(|criterion| !criterion.checked) {
+                        refused.push(format!(
+                            "line {}: task #{} has unchecked acceptance criteria; close it with `pearls tasks close --id {}`",
+                            row.line, row.id, row.id
+                        ));
+                    }
+                }
+                updates.push(update);
+            }
+            None => missing.push((row.line, row.id)),
+        }
+    }
+    if !refused.is_empty() {
+        for message in &refused {
+            fail!("{message}");
+        }
+        fail!("no tasks were updated");
+        return;
+    }
+
+    // All rows or none: a failure partway through rolls back the rows
+    // already applied.
+    let applied = async {
+        let txn = conn.begin().await?;
+        for update in &updates {
+            db::tasks::update_metadata(
+                &txn,
+                update.id,
+                update.title.as_deref(),
+                update.desc.as_deref(),
+                update.priority,
+                update.state,
+            )
+            .await
+            .map_err(|err| DbErr::Custom(format!("failed to update task #{}: {err}", update.id)))?;
+        }
+        txn.commit().await
+    };
+    if let Err(err) = applied.await {
+        fail!("{err}; no tasks were updated");
+        return;
+    }
+
+    let changes: Vec<&formats::csv::FieldChange> =
+        updates.iter().flat_map(|update| &update.changes).collect();
+    if json_output {
+        print_json(&schema::CsvUpdateSummary {
+            updated: updates.len(),
+            unchanged,
+            missing: missing.iter().map(|(_, id)| *id).collect(),
+            changes,
+        });
+    } else {
+        for change in &changes {
+            println!(
+                "#{} {}: {:?} -> {:?}",
+                change.id, change.field, change.from, change.to
+            );
+        }
+        println!("updated {} tasks, {unchanged} unchanged", updates.len());
+        for (line, id) in &missing {
+            eprintln!("warning: line {line}: task {id} not found, skipped");
+        }
+    }
+}
+
+/// How often `claim-next --wait` checks whether the database changed.
+const CLAIM_WAIT_POLL: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Claims the next ready task, sleeping until another process commits (or a
+/// retry backoff runs out) whenever nothing is ready. Each attempt holds the
+/// lock only while claiming, so writers that could unblock work aren't shut
+/// out.
+async fn claim_next_waiting(
+    conn: &DatabaseConnection,
+    db_path: &std::path::Path,
+    lock: &mut FileLock,
+    schedule: cli::Schedule,
+    timeout: Option<std::time::Duration>,
+) -> Result<Option<db::tasks::TaskRow>, DbErr> {
+    // A timeout too long to represent is as good as none.
+    let deadline = timeout.and_then(|timeout| std::time::Instant::now().checked_add(timeout));
+    let watcher = db::conn::connect_single(db_path).await?;
+    loop {
+        // Read the version before trying, so a commit that lands between the
+        // attempt and the wait still wakes us.
+        let version = db::watch::data_version(&watcher).await?;
+        {
+            let _guard = lock
+                .lock()
+                .map_err(|err| DbErr::Custom(format!("failed to lock database: {err}")))?;
+            if let Some(task) = db::tasks::claim_next(conn, schedule).await? {
+                return Ok(Some(task));
+            }
+        }
+        let retry_at = db::tasks::next_claimable_at(conn).await?.and_then(|at| {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|now| now.as_secs())
+                .unwrap_or_default();
+            let wait = u64::try_from(at).unwrap_or_default().saturating_sub(now);
+            std::time::Instant::now().checked_add(std::time::Duration::from_secs(wait))
+        });
+        let wake = match (deadline, retry_at) {
+            (Some(deadline), Some(retry_at)) => Some(deadline.min(retry_at)),
+            (deadline, retry_at) => deadline.or(retry_at),
+        };
+        let changed = db::watch::wait_for_change(&watcher, version, CLAIM_WAIT_POLL, wake).await?;
+        if !changed && deadline.is_some_and(|deadline| std::time::Instant::now() >= deadline) {
+            return Ok(None);
+        }
+    }
+}
+
+/// Polls `data_version` and prints one line per task event until stdout
+/// closes. JSON events are compact so the stream can be read line by line.
+async fn watch(
+    conn: &DatabaseConnection,
+    interval: std::time::Duration,
+    json_output: bool,
+) -> Result<(), DbErr> {
+    let mut version = db::watch::data_version(conn).await?;
+    let mut snapshot = db::watch::snapshot(conn).await?;
+    let mut retry_at = db::tasks::next_claimable_at(conn).await?;
+    loop {
+        tokio::time::sleep(interval).await;
+        let current = db::watch::data_version(conn).await?;
+        // Deferred tasks turn ready when their time comes, without a write.
+        let due = retry_at.is_some_and(|at| at <= db::tasks::unix_now());
+        if current == version && !due {
+            continue;
+        }
+        version = current;
+
+        let latest = db::watch::snapshot(conn).await?;
+        let events = db::watch::diff(&snapshot, &latest);
+        snapshot = latest;
+        retry_at = db::tasks::next_claimable_at(conn).await?;
+
+        let mut out = std::io::stdout().lock();
+        for event in &events {
+            let line = if json_output {
+                serde_json::to_value(event)
+                    .map(schema::versioned)
+                    .map(|value| value.to_string())
+                    .map_err(|err| DbErr::Custom(err.to_string()))?
+            } else {
+                format!("{} {}", event.event.as_str(), event.task.display_line())
+            };
+            if writeln!(out, "{line}").and_then(|_| out.flush()).is_err() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Reports why `task` can't be closed, if it can't. `tasks close` asks
+/// before running the verify command and again once it holds the lock.
+fn refuse_close(task: &db::tasks::TaskRow, force: bool) -> bool {
+    let id = task.id;
+    if task.state == "closed" {
+        fail!("task #{id} is already closed");
+        return true;
+    }
+    let unchecked = task.unchecked_criteria();
+    if unchecked > 0 && !force {
+        fail!("refusing to close #{id}: {unchecked} acceptance criteria unchecked");
+        fail!("{}", task.display_detail());
+        fail!("check them with `pearls tasks criteria check`, or pass --force");
+        return true;
+    }
+    false
+}
+
+/// Runs hooks for `events` and reports hooks that failed. Hooks never undo
+/// the write that triggered them.
+fn run_hooks(hooks: &hooks::Hooks, events: Result<Vec<db::watch::TaskEvent>, DbErr>) {
+    match events {
+        Ok(events) => {
+            for failure in hooks.run(&events) {
+                eprintln!("warning: {failure}");
+            }
+        }
+        Err(err) => eprintln!("warning: failed to check which hooks to run: {err}"),
+    }
+}
+
+/// Serializes `value` and keeps only the requested `--fields`.
+fn project<T: Serialize>(value: &T, fields: &[&'static str]) -> serde_json::Value {
+    output::project(serde_json::to_value(value).unwrap_or_default(), fields)
+}
+
+fn print_schemas(command: Option<&str>) {
+    let schemas = schema::command_schemas();
+    let payload = match command {
+        None => serde_json::Value::Object(
+            schemas
+                .into_iter()
+                .map(|(name, schema)| (name.to_string(), schema.to_value()))
+                .collect(),
+        ),
+        Some(command) => match schemas.into_iter().find(|(name, _)| *name == command) {
+            Some((_, schema)) => schema.to_value(),
+            None => {
+                let names: Vec<&str> = schema::command_schemas()
+                    .into_iter()
+                    .map(|(name, _)| name)
+                    .collect();
+                fail!(
+                    "unknown command {command:?} (expected one of: {})",
+                    names.join(", ")
+                );
+                return;
             }
         },
+    };
+    match serde_json::to_string_pretty(&payload) {
+        Ok(payload) => println!("{payload}"),
+        Err(err) => fail!("failed to serialize json: {err}"),
     }
 }
 
+/// Prints `value` as pretty JSON, tagging top-level objects with
+/// `schema_version`.
 fn print_json<T: Serialize>(value: &T) {
-    match serde_json::to_string_pretty(value) {
+    let value = match serde_json::to_value(value) {
+        Ok(value) => schema::versioned(value),
+        Err(err) => {
+            fail!("failed to serialize json: {err}");
+            return;
+        }
+    };
+    match serde_json::to_string_pretty(&value) {
         Ok(payload) => println!("{payload}"),
-        Err(err) => eprintln!("failed to serialize json: {err}"),
+        Err(err) => fail!("failed to serialize json: {err}"),
     }
 }
@@ -0,0 +1,499 @@
+use std::io::IsTerminal;
+use std::str::FromStr;
+
+use serde_json::{Map, Value};
+
+use crate::db::tasks::{self, TaskRow};
+
+/// Task fields that `--format` templates and `--fields` can refer to, in the
+/// order they appear in JSON output.
+pub const TASK_FIELDS: [&str; 16] = [
+    "id",
+    "title",
+    "desc",
+    "priority",
+    "state",
+    "key",
+    "verify",
+    "attempts",
+    "not_before",
+    "due_at",
+    "effective_priority",
+    "created_at",
+    "updated_at",
+    "parents",
+    "children",
+    "criteria",
+];
+
+/// Narrowest the title (and, in wide mode, description) column is allowed to
+/// shrink to before the table simply overflows the terminal.
+const MIN_TEXT_WIDTH: usize = 12;
+
+/// How text output should look on the current stdout.
+#[derive(Debug, Clone, Copy)]
+pub struct Style {
+    pub color: bool,
+    /// Terminal width to truncate to; `None` when stdout is not a terminal.
+    pub width: Option<usize>,
+}
+
+impl Style {
+    /// Colors only when stdout is a terminal, `--no-color` was not given, and
+    /// `NO_COLOR` is unset or empty (see https://no-color.org).
+    pub fn detect(no_color: bool) -> Style {
+        let tty = std::io::stdout().is_terminal();
+        let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        let width = tty
+            .then(|| terminal_size::terminal_size().map(|(width, _)| width.0 as usize))
+            .flatten();
+        Style {
+            color: tty && !no_color && !no_color_env,
+            width,
+        }
+    }
+
+    pub fn paint(&self, state: &str, text: &str) -> String {
+        if !self.color {
+            return text.to_string();
+        }
+        let code = match state {
+            "ready" => "32",
+            "in_progress" => "33",
+            "needs_human" => "35",
+            "blocked" => "31",
+            "deferred" => "34",
+            "closed" => "2",
+            "overdue" => "1;31",
+            _ => return text.to_string(),
+        };
+        format!("\x1b[{code}m{text}\x1b[0m")
+    }
+}
+
+/// Renders tasks as an aligned table of id, state, priority, title and open
+/// blockers, plus the description when `wide` is set. A due column (with
+/// overdue tasks highlighted) appears when any task has a due date. Text
+/// columns are truncated to fit `style.width`.
+pub fn render_table(tasks: &[TaskRow], style: Style, wide: bool) -> String {
+    if tasks.is_empty() {
+        return String::new();
+    }
+    let now = tasks::unix_now();
+    let show_due = tasks.iter().any(|task| task.due_at.is_some());
+
+    let ids: Vec<String> = tasks.iter().map(|task| format!("#{}", task.id)).collect();
+    let priorities: Vec<String> = tasks
+        .iter()
+        .map(|task| format!("p{}", task.priority))
+        .collect();
+    let blockers: Vec<String> = tasks
+        .iter()
+        .map(|task| {
+            task.blocked_by
+                .iter()
+                .map(|id| format!("#{id}"))
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .collect();
+    let dues: Vec<String> = tasks
+        .iter()
+        .map(|task| task.due_label(now).unwrap_or_default())
+        .collect();
+    let titles: Vec<&str> = tasks
+        .iter()
+        .map(|task| task.title.as_deref().unwrap_or(""))
+        .collect();
+    let descs: Vec<String> = tasks
+        .iter()
+        .map(|task| {
+            task.desc
+                .as_deref()
+                .unwrap_or("")
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect();
+
+    let id_width = column_width("ID", &ids);
+    let state_width = column_width("STATE", tasks.iter().map(|task| task.state.as_str()));
+    let priority_width = column_width("PRI", &priorities);
+    let blockers_width = column_width("BLOCKED BY", &blockers);
+    let due_width = if show_due {
+        column_width("DUE", &dues)
+    } else {
+        0
+    };
+    let mut title_width = column_width("TITLE", &titles);
+    let mut desc_width = column_width("DESC", &descs);
+
+    if let Some(width) = style.width {
+        let columns = 4 + usize::from(wide) + usize::from(show_due);
+        let fixed = id_width + state_width + priority_width + due_width + blockers_width;
+        let fixed = fixed + columns * 2;
+        let available = width.saturating_sub(fixed);
+        if wide {
+            if title_width + desc_width > available {
+                title_width = title_width.min((available * 2 / 5).max(MIN_TEXT_WIDTH));
+                desc_width = available.saturating_sub(title_width).max(MIN_TEXT_WIDTH);
+            }
+        } else {
+            title_width = title_width.min(available.max(MIN_TEXT_WIDTH));
+        }
+    }
+
+    let mut out = String::new();
+    let mut push_row = |cells: Vec<(String, usize, Option<&str>)>| {
+        let mut line = String::new();
+        for (index, (text, width, state)) in cells.iter().enumerate() {
+            if index > 0 {
+                line.push_str("  ");
+            }
+            let text = truncate(text, *width);
+            let padding = width.saturating_sub(text.chars().count());
+            match state {
+                Some(state) => line.push_str(&style.paint(state, &text)),
+                None => line.push_str(&text),
+            }
+            if index + 1 < cells.len() {
+                line.push_str(&" ".repeat(padding));
+            }
+        }
+        out.push_str(line.trim_end());
+        out.push('\n');
+    };
+
+    let mut header = vec![
+        ("ID".to_string(), id_width, None),
+        ("STATE".to_string(), state_width, None),
+        ("PRI".to_string(), priority_width, None),
+    ];
+    if show_due {
+        header.push(("DUE".to_string(), due_width, None));
+    }
+    header.push(("TITLE".to_string(), title_width, None));
+    header.push(("BLOCKED BY".to_string(), blockers_width, None));
+    if wide {
+        header.push(("DESC".to_string(), desc_width, None));
+    }
+    push_row(header);
+
+    for (index, task) in tasks.iter().enumerate() {
+        let mut cells = vec![
+            (ids[index].clone(), id_width, None),
+            (task.state.clone(), state_width, Some(task.state.as_str())),
+            (priorities[index].clone(), priority_width, None),
+        ];
+        if show_due {
+            let overdue = task.is_overdue(now).then_some("overdue");
+            cells.push((dues[index].clone(), due_width, overdue));
+        }
+        cells.push((titles[index].to_string(), title_width, None));
+        cells.push((blockers[index].clone(), blockers_width, None));
+        if wide {
+            cells.push((descs[index].clone(), desc_width, None));
+        }
+        push_row(cells);
+    }
+
+    out
+}
+
+/// A `--format` template such as `{id}\t{title}`. Placeholders name
+/// [`TASK_FIELDS`]; `{{`/`}}` produce literal braces and `\t`, `\n`, `\\`
+/// are unescaped so templates can be written in single-quoted shell strings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    parts: Vec<TemplatePart>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TemplatePart {
+    Literal(String),
+    Field(&'static str),
+}
+
+impl Template {
+    /// Tab-separated values of the given fields, used for `--fields` in text
+    /// mode.
+    pub fn from_fields(fields: &[&'static str]) -> Template {
+        let mut parts = Vec::new();
+        for (index, field) in fields.iter().enumerate() {
+            if index > 0 {
+                parts.push(TemplatePart::Literal("\t".to_string()));
+            }
+            parts.push(TemplatePart::Field(field));
+        }
+        Template { parts }
+    }
+
+    /// Interpolates the task's fields. Missing values render as empty
+    /// strings and id lists are joined with commas.
+    pub fn render(&self, task: &TaskRow) -> String {
+        let value = serde_json::to_value(task).unwrap_or(Value::Null);
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                TemplatePart::Literal(text) => out.push_str(text),
+                TemplatePart::Field(field) => out.push_str(&field_text(&value[*field])),
+            }
+        }
+        out
+    }
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = value.chars().peekable();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(ch) => name.push(ch),
+                            None => return Err(format!("unclosed placeholder {{{name}")),
+                        }
+                    }
+                    if !literal.is_empty() {
+                        parts.push(TemplatePart::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(TemplatePart::Field(parse_field(name.trim())?));
+                }
+                '}' => return Err("unmatched '}' (write '}}' for a literal brace)".to_string()),
+                '\\' => match chars.next() {
+                    Some('t') => literal.push('\t'),
+                    Some('n') => literal.push('\n'),
+                    Some('\\') => literal.push('\\'),
+                    Some(other) => {
+                        literal.push('\\');
+                        literal.push(other);
+                    }
+                    None => literal.push('\\'),
+                },
+                _ => literal.push(ch),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(TemplatePart::Literal(literal));
+        }
+        Ok(Template { parts })
+    }
+}
+
+/// Validates a field name against [`TASK_FIELDS`].
+pub fn parse_field(name: &str) -> Result<&'static str, String> {
+    TASK_FIELDS
+        .iter()
+        .copied()
+        .find(|field| *field == name)
+        .ok_or_else(|| {
+            format!(
+                "unknown field {name:?} (expected one of: {})",
+                TASK_FIELDS.join(", ")
+            )
+        })
+}
+
+/// Keeps only the given keys of a serialized task, or of every task when
+/// `value` is an array. An empty field list leaves the value untouched.
+pub fn project(value: Value, fields: &[&'static str]) -> Value {
+    if fields.is_empty() {
+        return value;
+    }
+    match value {
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(|item| project(item, fields))
+                .collect(),
+        ),
+        Value::Object(mut object) => {
+            let mut projected = Map::new();
+            for field in fields {
+                if let Some(value) = object.remove(*field) {
+                    projected.insert(field.to_string(), value);
+                }
+            }
+            Value::Object(projected)
+        }
+        other => other,
+    }
+}
+
+fn field_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        Value::Array(items) => items
+            .iter()
+            .map(field_text)
+            .collect::<Vec<_>>()
+            .join(","),
+        other => other.to_string(),
+    }
+}
+
+fn column_width<I, S>(header: &str, values: I) -> usize
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    values
+        .into_iter()
+        .map(|value| value.as_ref().chars().count())
+        .fold(header.len(), usize::max)
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(width.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(id: i64, title: &str, state: &str, blocked_by: &[i64]) -> TaskRow {
+        TaskRow {
+            id,
+            title: Some(title.to_string()),
+            desc: Some("a  longer\ndescription".to_string()),
+            priority: 1,
+            state: state.to_string(),
+            key: None,
+            verify: None,
+            attempts: 0,
+            not_before: None,
+            due_at: None,
+            effective_priority: None,
+            created_at: 0,
+            updated_at: 0,
+            parents: blocked_by.to_vec(),
+            children: Vec::new(),
+            criteria: Vec::new(),
+            blocked_by: blocked_by.to_vec(),
+        }
+    }
+
+    #[test]
+    fn render_table_aligns_and_truncates_to_width() {
+        let tasks = [
+            row(1, "Build the parser", "ready", &[]),
+            row(
+                12,
+                "Write a very long title that will not fit",
+                "blocked",
+                &[1],
+            ),
+        ];
+        let plain = Style {
+            color: false,
+            width: Some(48),
+        };
+
+        let table = render_table(&tasks, plain, false);
+        assert_eq!(
+            table,
+            "ID   STATE    PRI  TITLE              BLOCKED BY\n\
+             #1   ready    p1   Build the parser\n\
+             #12  blocked  p1   Write a very lon…  #1\n"
+        );
+
+        let wide = render_table(
+            &tasks,
+            Style {
+                width: None,
+                ..plain
+            },
+            true,
+        );
+        assert!(wide.lines().next().unwrap_or("").ends_with("DESC"));
+        assert!(wide.contains("a longer description"));
+    }
+
+    #[test]
+    fn render_table_adds_due_column_and_highlights_overdue_tasks() {
+        let now = tasks::unix_now();
+        let mut late = row(1, "Late", "ready", &[]);
+        late.due_at = Some(now - 2 * 3600 - 30);
+        let mut soon = row(2, "Soon", "ready", &[]);
+        soon.due_at = Some(now + 3 * 86_400 + 30);
+        let tasks = [late, soon, row(3, "Whenever", "ready", &[])];
+
+        let plain = Style {
+            color: false,
+            width: None,
+        };
+        assert_eq!(
+            render_table(&tasks, plain, false),
+            "ID  STATE  PRI  DUE            TITLE     BLOCKED BY\n\
+             #1  ready  p1   overdue by 2h  Late\n\
+             #2  ready  p1   due in 3d      Soon\n\
+             #3  ready  p1                  Whenever\n"
+        );
+
+        let colored = render_table(
+            &tasks,
+            Style {
+                color: true,
+                ..plain
+            },
+            false,
+        );
+        assert!(colored.contains("\x1b[1;31moverdue by 2h\x1b[0m"));
+        assert!(!colored.contains("\x1b[1;31mdue in"));
+    }
+
+    #[test]
+    fn template_interpolates_fields_and_projection_keeps_selected_keys() {
+        let task = row(7, "Ship it", "blocked", &[2, 3]);
+
+        let template: Template = "{id}\\t{title} [{parents}] {{{key}}}"
+            .parse()
+            .expect("parse template");
+        assert_eq!(template.render(&task), "7\tShip it [2,3] {}");
+        assert!("{nope}".parse::<Template>().is_err());
+        assert!("{id".parse::<Template>().is_err());
+
+        let fields = Template::from_fields(&["id", "state"]);
+        assert_eq!(fields.render(&task), "7\tblocked");
+
+        let value = serde_json::to_value(&task).expect("serialize");
+        let projected = project(value, &["id", "parents"]);
+        assert_eq!(projected, serde_json::json!({ "id": 7, "parents": [2, 3] }));
+    }
+
+    #[test]
+    fn paint_only_colors_when_enabled() {
+        let colored = Style {
+            color: true,
+            width: None,
+        };
+        assert_eq!(colored.paint("ready", "ready"), "\x1b[32mready\x1b[0m");
+        let plain = Style {
+            color: false,
+            width: None,
+        };
+        assert_eq!(plain.paint("ready", "ready"), "ready");
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use schemars::generate::SchemaSettings;
+use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+
+use crate::db::dump::{Dump, ImportSummary};
+use crate::db::notes::NoteRow;
+use crate::db::sync::MergeSummary;
+use crate::db::tasks::{TaskPage, TaskRow};
+use crate::db::watch::TaskEvent;
+use crate::formats::csv::FieldChange;
+
+/// Version of the `--json` output shapes. Every top-level JSON object carries
+/// it as `schema_version`; bump it whenever a field is removed, renamed, or
+/// changes meaning.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// `tasks list --count`.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct StateCounts {
+    pub states: BTreeMap<String, u64>,
+    pub total: u64,
+}
+
+/// Reply when a command had nothing to do, e.g. `no_ready_tasks`.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Status {
+    pub status: &'static str,
+}
+
+/// `tasks close` when the verify command fails and the task stays open.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct VerifyFailed {
+    #[schemars(extend("const" = "verify_failed"))]
+    pub status: &'static str,
+    /// Null when the command was killed by a signal.
+    pub exit_code: Option<i32>,
+    /// The note recording the command's output.
+    pub note: NoteRow,
+}
+
+/// `sync --export`.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SyncExport {
+    pub path: PathBuf,
+    pub tasks: usize,
+}
+
+/// `import --format csv --update`.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CsvUpdateSummary<'a> {
+    pub updated: usize,
+    pub unchanged: usize,
+    /// Ids from the sheet that matched no task.
+    pub missing: Vec<i64>,
+    pub changes: Vec<&'a FieldChange>,
+}
+
+/// Adds `schema_version` as the first key of a top-level JSON object.
+/// Arrays (`--bare-array`, `tasks search`) are left as they are.
+pub fn versioned(value: Value) -> Value {
+    match value {
+        Value::Object(object) => {
+            let mut versioned = Map::new();
+            versioned.insert("schema_version".to_string(), json!(SCHEMA_VERSION));
+            versioned.extend(object);
+            Value::Object(versioned)
+        }
+        other => other,
+    }
+}
+
+/// JSON Schema for the `--json` output of every command, keyed by a
+/// dash-joined command name such as `tasks-claim-next`.
+pub fn command_schemas() -> Vec<(&'static str, Schema)> {
+    vec![
+        ("tasks-list", versioned_root::<TaskPage>("tasks list")),
+        (
+            "tasks-list-bare-array",
+            root::<Vec<TaskRow>>("tasks list --bare-array"),
+        ),
+        (
+            "tasks-list-count",
+            versioned_root::<StateCounts>("tasks list --count"),
+        ),
+        (
+            "tasks-list-json-lines",
+            root::<TaskRow>("tasks list --json-lines (one object per line)"),
+        ),
+        ("tasks-search", root::<Vec<TaskRow>>("tasks search")),
+        ("tasks-show", versioned_root::<TaskRow>("tasks show")),
+        (
+            "tasks-claim-next",
+            task_or_status("tasks claim-next", "no_ready_tasks"),
+        ),
+        ("tasks-add", versioned_root::<TaskRow>("tasks add")),
+        (
+            "tasks-update-metadata",
+            task_or_status("tasks update-metadata", "no_changes"),
+        ),
+        (
+            "tasks-update-dependency",
+            versioned_root::<TaskRow>("tasks update-dependency"),
+        ),
+        ("tasks-close", task_or::<VerifyFailed>("tasks close")),
+        ("tasks-fail", versioned_root::<TaskRow>("tasks fail")),
+        ("tasks-snooze", versioned_root::<TaskRow>("tasks snooze")),
+        (
+            "tasks-criteria-add",
+            versioned_root::<TaskRow>("tasks criteria add"),
+        ),
+        (
+            "tasks-criteria-check",
+            versioned_root::<TaskRow>("tasks criteria check"),
+        ),
+        ("tasks-notes", root::<Vec<NoteRow>>("tasks notes")),
+        ("export", versioned_root::<Dump>("export --format json")),
+        ("import", versioned_root::<ImportSummary>("import")),
+        (
+            "import-update",
+            versioned_root::<CsvUpdateSummary>("import --format csv --update"),
+        ),
+        ("sync-export", versioned_root::<SyncExport>("sync --export")),
+        (
+            "sync-import",
+            versioned_root::<MergeSummary>("sync --import"),
+        ),
+        (
+            "watch",
+            versioned_root::<TaskEvent>("watch (one object per line)"),
+        ),
+    ]
+}
+
+fn generator() -> SchemaGenerator {
+    SchemaSettings::draft2020_12().into_generator()
+}
+
+fn root<T: JsonSchema>(title: &str) -> Schema {
+    let mut schema = generator().into_root_schema_for::<T>();
+    // Lead with `$schema` and `title` so the published files read top-down.
+    let mut ordered = Map::new();
+    if let Some(dialect) = schema.remove("$schema") {
+        ordered.insert("$schema".to_string(), dialect);
+    }
+    schema.remove("title");
+    ordered.insert("title".to_string(), json!(format!("pearls {title}")));
+    if let Value::Object(rest) = schema.to_value() {
+        ordered.extend(rest);
+    }
+    Schema::from(ordered)
+}
+
+fn versioned_root<T: JsonSchema>(title: &str) -> Schema {
+    with_schema_version(root::<T>(title))
+}
+
+/// A task on success, or a [`Status`] whose `status` is `status`.
+fn task_or_status(title: &str, status: &str) -> Schema {
+    task_or_schema(title, |_| {
+        json_schema!({
+            "type": "object",
+            "properties": { "status": { "const": status } },
+            "required": ["status"]
+        })
+    })
+}
+
+/// A task on success, or a `T` describing why there is none.
+fn task_or<T: JsonSchema>(title: &str) -> Schema {
+    task_or_schema(title, |generator| generator.subschema_for::<T>())
+}
+
+fn task_or_schema(title: &str, other: impl FnOnce(&mut SchemaGenerator) -> Schema) -> Schema {
+    let mut generator = generator();
+    let task = generator.subschema_for::<TaskRow>();
+    let other = other(&mut generator);
+    let mut schema = json_schema!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": format!("pearls {title}"),
+        "anyOf": [task, other]
+    });
+    let definitions: Map<String, Value> = generator.take_definitions(true);
+    schema.insert("$defs".to_string(), Value::Object(definitions));
+    with_schema_version(schema)
+}
+
+fn with_schema_version(mut schema: Schema) -> Schema {
+    let version = json!({ "type": "integer", "const": SCHEMA_VERSION });
+    let mut properties = Map::new();
+    properties.insert("schema_version".to_string(), version);
+    if let Some(Value::Object(existing)) = schema.remove("properties") {
+        properties.extend(existing);
+    }
+    schema.insert("properties".to_string(), Value::Object(properties));
+
+    let mut required = vec![json!("schema_version")];
+    if let Some(Value::Array(existing)) = schema.remove("required") {
+        required.extend(existing);
+    }
+    schema.insert("required".to_string(), Value::Array(required));
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versioned_schemas_require_schema_version() {
+        for (name, schema) in command_schemas() {
+            let value = schema.as_value();
+            let versioned = value["properties"]["schema_version"]["const"] == json!(SCHEMA_VERSION);
+            let is_array = value["type"] == json!("array");
+            assert!(
+                versioned || is_array || name == "tasks-list-json-lines",
+                "{name} is missing schema_version"
+            );
+        }
+
+        let payload = versioned(json!({ "id": 1 }));
+        assert_eq!(
+            serde_json::to_string(&payload).expect("serialize"),
+            r#"{"schema_version":1,"id":1}"#
+        );
+        assert_eq!(versioned(json!([1])), json!([1]));
+    }
+}
@@ -0,0 +1,352 @@
+use std::collections::HashSet;
+
+use crate::cli::TaskState;
+use crate::db::notes::NoteRow;
+use crate::db::tasks::TaskRow;
+
+/// States in the order the list groups them: tasks waiting on a person, then
+/// work in flight.
+pub const GROUPS: [&str; 6] = [
+    "needs_human",
+    "in_progress",
+    "ready",
+    "blocked",
+    "deferred",
+    "closed",
+];
+
+/// Edits bound to keys in the list.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    RaisePriority,
+    LowerPriority,
+    Close,
+    Release,
+    Reopen,
+}
+
+/// Fields an [`Action`] changes, ready for `update_metadata`.
+#[derive(Debug)]
+pub struct Change {
+    pub priority: Option<i64>,
+    pub state: Option<TaskState>,
+    pub message: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ListRow {
+    Header {
+        state: &'static str,
+        count: usize,
+    },
+    /// Index into [`App::tasks`].
+    Task(usize),
+}
+
+/// One line of the dependency tree panel.
+#[derive(Debug, PartialEq)]
+pub struct TreeLine {
+    pub prefix: String,
+    pub id: i64,
+    /// Set when the task was already shown higher up the tree.
+    pub repeated: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct App {
+    pub tasks: Vec<TaskRow>,
+    pub selected: Option<i64>,
+    pub show_closed: bool,
+    pub status: String,
+    /// Notes on the selected task, oldest first.
+    pub notes: Vec<NoteRow>,
+}
+
+impl App {
+    /// Replaces the task list, keeping the selection on the same task when it
+    /// is still visible.
+    pub fn set_tasks(&mut self, tasks: Vec<TaskRow>) {
+        self.tasks = tasks;
+        self.fix_selection();
+    }
+
+    pub fn toggle_closed(&mut self) {
+        self.show_closed = !self.show_closed;
+        self.fix_selection();
+    }
+
+    /// Group headers and tasks in display order; tasks within a group are
+    /// sorted by priority, then id.
+    pub fn rows(&self) -> Vec<ListRow> {
+        let mut rows = Vec::new();
+        for state in GROUPS {
+            if state == "closed" && !self.show_closed {
+                continue;
+            }
+            let mut members: Vec<usize> = (0..self.tasks.len())
+                .filter(|&index| self.tasks[index].state == state)
+                .collect();
+            if members.is_empty() {
+                continue;
+            }
+            members.sort_by_key(|&index| (self.tasks[index].priority, self.tasks[index].id));
+            rows.push(ListRow::Header {
+                state,
+                count: members.len(),
+            });
+            rows.extend(members.into_iter().map(ListRow::Task));
+        }
+        rows
+    }
+
+    pub fn move_selection(&mut self, delta: isize) {
+        let ids = self.visible_ids();
+        if ids.is_empty() {
+            self.selected = None;
+            return;
+        }
+        let current = self
+            .selected
+            .and_then(|id| ids.iter().position(|other| *other == id))
+            .unwrap_or(0);
+        let next = current.saturating_add_signed(delta).min(ids.len() - 1);
+        self.selected = Some(ids[next]);
+    }
+
+    pub fn task(&self, id: i64) -> Option<&TaskRow> {
+        self.tasks.iter().find(|task| task.id == id)
+    }
+
+    pub fn selected_task(&self) -> Option<&TaskRow> {
+        self.selected.and_then(|id| self.task(id))
+    }
+
+    /// The selected task's descendants as an indented tree. Tasks reachable
+    /// along several paths are expanded once and marked as repeats after that.
+    pub fn dependency_tree(&self, id: i64) -> Vec<TreeLine> {
+        let mut lines = vec![TreeLine {
+            prefix: String::new(),
+            id,
+            repeated: false,
+        }];
+        let mut seen = HashSet::from([id]);
+        self.push_children(id, "", &mut seen, &mut lines);
+        lines
+    }
+
+    fn push_children(
+        &self,
+        id: i64,
+        indent: &str,
+        seen: &mut HashSet<i64>,
+        lines: &mut Vec<TreeLine>,
+    ) {
+        let Some(task) = self.task(id) else {
+            return;
+        };
+        let mut children = task.children.clone();
+        children.sort_unstable();
+        for (index, child) in children.iter().enumerate() {
+            let last = index + 1 == children.len();
+            let branch = if last { "└─ " } else { "├─ " };
+            let repeated = !seen.insert(*child);
+            lines.push(TreeLine {
+                prefix: format!("{indent}{branch}"),
+                id: *child,
+                repeated,
+            });
+            if !repeated {
+                let next = format!("{indent}{}", if last { "   " } else { "│  " });
+                self.push_children(*child, &next, seen, lines);
+            }
+        }
+    }
+
+    fn visible_ids(&self) -> Vec<i64> {
+        self.rows()
+            .into_iter()
+            .filter_map(|row| match row {
+                ListRow::Task(index) => Some(self.tasks[index].id),
+                ListRow::Header { .. } => None,
+            })
+            .collect()
+    }
+
+    fn fix_selection(&mut self) {
+        let ids = self.visible_ids();
+        if !self.selected.is_some_and(|id| ids.contains(&id)) {
+            self.selected = ids.first().copied();
+        }
+    }
+}
+
+/// Works out what `action` changes on `task`, or why it doesn't apply.
+/// Priority never drops below zero (lower is more important).
+pub fn plan(action: Action, task: &TaskRow) -> Result<Change, String> {
+    let id = task.id;
+    match action {
+        Action::RaisePriority => {
+            if task.priority <= 0 {
+                return Err(format!("#{id} already has the highest priority"));
+            }
+            let priority = task.priority - 1;
+            Ok(Change {
+                priority: Some(priority),
+                state: None,
+                message: format!("#{id} priority {priority}"),
+            })
+        }
+        Action::LowerPriority => {
+            let priority = task.priority + 1;
+            Ok(Change {
+                priority: Some(priority),
+                state: None,
+                message: format!("#{id} priority {priority}"),
+            })
+        }
+        Action::Close if task.state == "closed" => Err(format!("#{id} is already closed")),
+        Action::Close if task.verify.is_some() => Err(format!(
+            "#{id} has a verify command (use `pearls tasks close --id {id}`)"
+        )),
+        Action::Close if task.unchecked_criteria() > 0 => Err(format!(
+            "#{id} has {} unchecked criteria (use `pearls tasks close --force`)",
+            task.unchecked_criteria()
+        )),
+        Action::Close => Ok(Change {
+            priority: None,
+            state: Some(TaskState::Closed),
+            message: format!("closed #{id}"),
+        }),
+        Action::Release if task.state != "in_progress" => Err(format!("#{id} is not in progress")),
+        Action::Release => Ok(Change {
+            priority: None,
+            state: Some(TaskState::Ready),
+            message: format!("released #{id}"),
+        }),
+        Action::Reopen if task.state == "needs_human" => Ok(Change {
+            priority: None,
+            state: Some(TaskState::Ready),
+            message: format!("requeued #{id}"),
+        }),
+        Action::Reopen if task.state != "closed" => Err(format!("#{id} is not closed")),
+        Action::Reopen => Ok(Change {
+            priority: None,
+            state: Some(TaskState::Ready),
+            message: format!("reopened #{id}"),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(id: i64, state: &str, priority: i64, children: &[i64]) -> TaskRow {
+        TaskRow {
+            id,
+            title: Some(format!("task {id}")),
+            desc: None,
+            priority,
+            state: state.to_string(),
+            key: None,
+            verify: None,
+            attempts: 0,
+            not_before: None,
+            due_at: None,
+            effective_priority: None,
+            created_at: 0,
+            updated_at: 0,
+            parents: Vec::new(),
+            children: children.to_vec(),
+            criteria: Vec::new(),
+            blocked_by: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn rows_group_by_state_and_selection_survives_refresh() {
+        let mut app = App::default();
+        app.set_tasks(vec![
+            row(1, "ready", 2, &[2, 3]),
+            row(2, "blocked", 1, &[4]),
+            row(3, "in_progress", 1, &[4]),
+            row(4, "closed", 1, &[]),
+            row(5, "ready", 1, &[]),
+        ]);
+        assert_eq!(
+            app.rows(),
+            vec![
+                ListRow::Header {
+                    state: "in_progress",
+                    count: 1
+                },
+                ListRow::Task(2),
+                ListRow::Header {
+                    state: "ready",
+                    count: 2
+                },
+                ListRow::Task(4),
+                ListRow::Task(0),
+                ListRow::Header {
+                    state: "blocked",
+                    count: 1
+                },
+                ListRow::Task(1),
+            ]
+        );
+        assert_eq!(app.selected, Some(3));
+        app.move_selection(2);
+        assert_eq!(app.selected, Some(1));
+        app.move_selection(10);
+        assert_eq!(app.selected, Some(2));
+
+        let tasks = std::mem::take(&mut app.tasks);
+        app.set_tasks(tasks.into_iter().rev().collect());
+        assert_eq!(app.selected, Some(2));
+
+        let tree: Vec<(String, i64, bool)> = app
+            .dependency_tree(1)
+            .into_iter()
+            .map(|line| (line.prefix, line.id, line.repeated))
+            .collect();
+        assert_eq!(
+            tree,
+            vec![
+                (String::new(), 1, false),
+                ("├─ ".to_string(), 2, false),
+                ("│  └─ ".to_string(), 4, false),
+                ("└─ ".to_string(), 3, false),
+                ("   └─ ".to_string(), 4, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn plan_rejects_actions_that_do_not_apply() {
+        let working = row(1, "in_progress", 0, &[]);
+        assert!(plan(Action::RaisePriority, &working).is_err());
+        assert_eq!(
+            plan(Action::LowerPriority, &working).map(|change| change.priority),
+            Ok(Some(1))
+        );
+        assert!(plan(Action::Release, &working).is_ok());
+        assert!(plan(Action::Reopen, &working).is_err());
+
+        let mut verified = row(4, "ready", 1, &[]);
+        verified.verify = Some("cargo test".to_string());
+        assert!(plan(Action::Close, &verified).is_err());
+
+        let closed = row(2, "closed", 1, &[]);
+        assert!(plan(Action::Close, &closed).is_err());
+        assert!(matches!(
+            plan(Action::Reopen, &closed).map(|change| change.state),
+            Ok(Some(TaskState::Ready))
+        ));
+
+        let parked = row(3, "needs_human", 1, &[]);
+        assert!(matches!(
+            plan(Action::Reopen, &parked).map(|change| change.state),
+            Ok(Some(TaskState::Ready))
+        ));
+    }
+}
@@ -0,0 +1,135 @@
+mod app;
+mod ui;
+
+use std::time::{Duration, Instant};
+
+use filelock::FileLock;
+use ratatui::DefaultTerminal;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use sea_orm_migration::sea_orm::{DatabaseConnection, DbErr};
+
+use crate::cli::TaskState;
+use crate::db::notes;
+use crate::db::tasks::{self, TaskRow};
+use crate::hooks::Hooks;
+use app::{Action, App};
+
+/// How often the list is reloaded so changes made by agents show up.
+const REFRESH: Duration = Duration::from_secs(2);
+
+/// Runs the interactive UI until the user quits. Edits take `lock` for the
+/// duration of each write, like the other write commands, and run `hooks`
+/// once the lock is released.
+pub async fn run(
+    conn: &DatabaseConnection,
+    lock: &mut FileLock,
+    hooks: Hooks,
+) -> Result<(), DbErr> {
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, conn, lock, &hooks).await;
+    ratatui::restore();
+    result
+}
+
+async fn event_loop(
+    terminal: &mut DefaultTerminal,
+    conn: &DatabaseConnection,
+    lock: &mut FileLock,
+    hooks: &Hooks,
+) -> Result<(), DbErr> {
+    let mut app = App::default();
+    app.set_tasks(load(conn).await?);
+    let mut last_refresh = Instant::now();
+    let mut notes_loaded = false;
+    let mut notes_for = None;
+
+    loop {
+        if !notes_loaded || notes_for != app.selected {
+            app.notes = match app.selected {
+                Some(id) => notes::list_notes(conn, id).await?,
+                None => Vec::new(),
+            };
+            notes_loaded = true;
+            notes_for = app.selected;
+        }
+        terminal
+            .draw(|frame| ui::draw(frame, &app))
+            .map_err(|err| DbErr::Custom(format!("failed to draw: {err}")))?;
+
+        let timeout = REFRESH.saturating_sub(last_refresh.elapsed());
+        let mut dirty = false;
+        if event::poll(timeout).map_err(terminal_err)?
+            && let Event::Key(key) = event::read().map_err(terminal_err)?
+            && key.kind == KeyEventKind::Press
+        {
+            let action = match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Down | KeyCode::Char('j') => {
+                    app.move_selection(1);
+                    None
+                }
+                KeyCode::Up | KeyCode::Char('k') => {
+                    app.move_selection(-1);
+                    None
+                }
+                KeyCode::Char('a') => {
+                    app.toggle_closed();
+                    None
+                }
+                KeyCode::Char('+') | KeyCode::Char('=') => Some(Action::RaisePriority),
+                KeyCode::Char('-') => Some(Action::LowerPriority),
+                KeyCode::Char('c') => Some(Action::Close),
+                KeyCode::Char('r') => Some(Action::Release),
+                KeyCode::Char('o') => Some(Action::Reopen),
+                _ => None,
+            };
+            if let Some(action) = action
+                && let Some(task) = app.selected_task()
+            {
+                let id = task.id;
+                app.status = match app::plan(action, task) {
+                    Ok(change) => match apply(conn, lock, hooks, id, &change).await {
+                        Ok(failures) if !failures.is_empty() => {
+                            format!("{}; {}", change.message, failures.join("; "))
+                        }
+                        Ok(_) => change.message,
+                        Err(err) => format!("#{id} not updated: {err}"),
+                    },
+                    Err(reason) => reason,
+                };
+                dirty = true;
+            }
+        }
+
+        if dirty || last_refresh.elapsed() >= REFRESH {
+            app.set_tasks(load(conn).await?);
+            last_refresh = Instant::now();
+            notes_loaded = false;
+        }
+    }
+}
+
+async fn load(conn: &DatabaseConnection) -> Result<Vec<TaskRow>, DbErr> {
+    tasks::list_tasks(conn, &TaskState::ALL).await
+}
+
+async fn apply(
+    conn: &DatabaseConnection,
+    lock: &mut FileLock,
+    hooks: &Hooks,
+    id: i64,
+    change: &app::Change,
+) -> Result<Vec<String>, DbErr> {
+    let guard = lock
+        .lock()
+        .map_err(|err| DbErr::Custom(format!("failed to lock database: {err}")))?;
+    let before = hooks.track(conn, &[id]).await?;
+    tasks::update_metadata(conn, id, None, None, change.priority, change.state).await?;
+    let events = hooks.events(conn, &before).await?;
+    drop(guard);
+    Ok(hooks.run(&events))
+}
+
+fn terminal_err(err: std::io::Error) -> DbErr {
+    DbErr::Custom(format!("terminal error: {err}"))
+}
@@ -0,0 +1,297 @@
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+
+use crate::db::tasks::unix_now;
+use crate::tui::app::{App, ListRow};
+
+const HELP: &str = "j/k move  +/- priority  c close  r release  o reopen  a closed  q quit";
+
+pub fn draw(frame: &mut Frame, app: &App) {
+    let [main, footer] =
+        Layout::vertical([Constraint::Min(3), Constraint::Length(1)]).areas(frame.area());
+    let [list, side] =
+        Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(main);
+    let [detail, tree] =
+        Layout::vertical([Constraint::Percentage(55), Constraint::Percentage(45)]).areas(side);
+
+    draw_list(frame, app, list);
+    draw_detail(frame, app, detail);
+    draw_tree(frame, app, tree);
+
+    let status = if app.status.is_empty() {
+        HELP.to_string()
+    } else {
+        format!("{}  |  {HELP}", app.status)
+    };
+    frame.render_widget(
+        Paragraph::new(status).style(Style::new().add_modifier(Modifier::DIM)),
+        footer,
+    );
+}
+
+fn draw_list(frame: &mut Frame, app: &App, area: Rect) {
+    let rows = app.rows();
+    let now = unix_now();
+    let mut state = ListState::default();
+    let items: Vec<ListItem> = rows
+        .iter()
+        .enumerate()
+        .map(|(position, row)| match row {
+            ListRow::Header { state: name, count } => ListItem::new(Line::from(Span::styled(
+                format!("{} ({count})", heading(name)),
+                state_style(name).add_modifier(Modifier::BOLD),
+            ))),
+            ListRow::Task(index) => {
+                let task = &app.tasks[*index];
+                if app.selected == Some(task.id) {
+                    state.select(Some(position));
+                }
+                let title = if task.is_overdue(now) {
+                    Style::new().fg(Color::Red).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::new()
+                };
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!("  #{:<4} ", task.id)),
+                    Span::styled(format!("p{} ", task.priority), Style::new().fg(Color::Cyan)),
+                    Span::styled(task.title.clone().unwrap_or_default(), title),
+                ]))
+            }
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(Block::bordered().title(" Tasks "))
+        .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+fn draw_detail(frame: &mut Frame, app: &App, area: Rect) {
+    let block = Block::bordered().title(" Detail ");
+    let Some(task) = app.selected_task() else {
+        frame.render_widget(Paragraph::new("no tasks").block(block), area);
+        return;
+    };
+    let now = unix_now();
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("#{} {}", task.id, task.title.as_deref().unwrap_or("")),
+            Style::new().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(vec![
+            Span::styled(task.state.clone(), state_style(&task.state)),
+            Span::raw(format!("  priority {}", task.priority)),
+            Span::raw(
+                task.key
+                    .as_ref()
+                    .map(|key| format!("  key {key}"))
+                    .unwrap_or_default(),
+            ),
+            Span::styled(
+                task.due_label(now)
+                    .map(|due| format!("  {due}"))
+                    .unwrap_or_default(),
+                if task.is_overdue(now) {
+                    Style::new().fg(Color::Red)
+                } else {
+                    Style::new()
+                },
+            ),
+        ]),
+        Line::default(),
+    ];
+    lines.extend(
+        task.desc
+            .as_deref()
+            .unwrap_or("")
+            .lines()
+            .map(|line| Line::from(line.to_string())),
+    );
+    if !task.criteria.is_empty() {
+        lines.push(Line::default());
+        lines.push(Line::from("Criteria:"));
+        for criterion in &task.criteria {
+            let (mark, style) = if criterion.checked {
+                ("[x]", Style::new().fg(Color::Green))
+            } else {
+                ("[ ]", Style::new())
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("  {mark} "), style),
+                Span::raw(criterion.text.clone()),
+            ]));
+        }
+    }
+    if !app.notes.is_empty() {
+        lines.push(Line::default());
+        lines.push(Line::from("Notes:"));
+        for note in &app.notes {
+            // Verify output can be long; the first line says what happened.
+            let mut body = note.body.lines();
+            lines.push(Line::from(format!("  - {}", body.next().unwrap_or(""))));
+            let more = body.count();
+            if more > 0 {
+                lines.push(Line::from(Span::styled(
+                    format!(
+                        "    ({more} more; see `pearls tasks notes --id {}`)",
+                        task.id
+                    ),
+                    Style::new().add_modifier(Modifier::DIM),
+                )));
+            }
+        }
+    }
+    lines.push(Line::default());
+    lines.push(Line::from("Parents:"));
+    lines.extend(related(app, &task.parents));
+    lines.push(Line::from("Children:"));
+    lines.extend(related(app, &task.children));
+
+    frame.render_widget(
+        Paragraph::new(lines)
+            .block(block)
+            .wrap(Wrap { trim: false }),
+        area,
+    );
+}
+
+fn draw_tree(frame: &mut Frame, app: &App, area: Rect) {
+    let lines: Vec<Line> = match app.selected {
+        Some(id) => app
+            .dependency_tree(id)
+            .into_iter()
+            .map(|line| {
+                let (title, state) = app
+                    .task(line.id)
+                    .map(|task| (task.title.clone().unwrap_or_default(), task.state.as_str()))
+                    .unwrap_or_default();
+                let mut spans = vec![
+                    Span::raw(line.prefix),
+                    Span::styled(format!("#{}", line.id), state_style(state)),
+                    Span::raw(format!(" {title}")),
+                ];
+                if line.repeated {
+                    spans.push(Span::styled(
+                        " (see above)",
+                        Style::new().add_modifier(Modifier::DIM),
+                    ));
+                }
+                Line::from(spans)
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(" Dependents ")),
+        area,
+    );
+}
+
+fn related(app: &App, ids: &[i64]) -> Vec<Line<'static>> {
+    if ids.is_empty() {
+        return vec![Line::from("  (none)")];
+    }
+    ids.iter()
+        .map(|id| match app.task(*id) {
+            Some(task) => Line::from(vec![
+                Span::raw("  "),
+                Span::styled(format!("#{id} [{}]", task.state), state_style(&task.state)),
+                Span::raw(format!(" {}", task.title.as_deref().unwrap_or(""))),
+            ]),
+            None => Line::from(format!("  #{id}")),
+        })
+        .collect()
+}
+
+fn heading(state: &str) -> &'static str {
+    match state {
+        "in_progress" => "In Progress",
+        "needs_human" => "Needs Human",
+        "ready" => "Ready",
+        "blocked" => "Blocked",
+        "deferred" => "Deferred",
+        _ => "Closed",
+    }
+}
+
+/// Same palette as the table output: ready green, in progress yellow,
+/// needs human magenta, blocked red, deferred blue, closed dim.
+fn state_style(state: &str) -> Style {
+    match state {
+        "ready" => Style::new().fg(Color::Green),
+        "in_progress" => Style::new().fg(Color::Yellow),
+        "needs_human" => Style::new().fg(Color::Magenta),
+        "blocked" => Style::new().fg(Color::Red),
+        "deferred" => Style::new().fg(Color::Blue),
+        "closed" => Style::new().add_modifier(Modifier::DIM),
+        _ => Style::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+
+    use super::*;
+    use crate::db::notes::NoteRow;
+    use crate::db::tasks::TaskRow;
+
+    #[test]
+    fn draw_shows_groups_detail_and_tree() {
+        let task = |id: i64, state: &str, parents: &[i64], children: &[i64]| TaskRow {
+            id,
+            title: Some(format!("task {id}")),
+            desc: Some("details here".to_string()),
+            priority: 1,
+            state: state.to_string(),
+            key: None,
+            verify: None,
+            attempts: 0,
+            not_before: None,
+            due_at: None,
+            effective_priority: None,
+            created_at: 0,
+            updated_at: 0,
+            parents: parents.to_vec(),
+            children: children.to_vec(),
+            criteria: Vec::new(),
+            blocked_by: Vec::new(),
+        };
+        let mut app = App::default();
+        app.set_tasks(vec![
+            task(1, "in_progress", &[], &[2]),
+            task(2, "blocked", &[1], &[]),
+        ]);
+        app.notes = vec![NoteRow {
+            id: 1,
+            task_id: 1,
+            body: "verify failed (exit status: 1): make test\nerror: boom".to_string(),
+            created_at: 0,
+        }];
+
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).expect("terminal");
+        terminal.draw(|frame| draw(frame, &app)).expect("draw");
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        for expected in [
+            "In Progress (1)",
+            "Blocked (1)",
+            "details here",
+            "- verify failed (exit status: 1): make test",
+            "(1 more; see",
+            "└─ #2 task 2",
+        ] {
+            assert!(screen.contains(expected), "missing {expected:?}");
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use std::io::Read;
+use std::process::{Command, ExitStatus, Stdio};
+
+/// How much of a failing command's output is kept in the note; the end of
+/// the output is usually where the failure is.
+const MAX_OUTPUT: usize = 16 * 1024;
+
+pub struct Outcome {
+    pub status: ExitStatus,
+    /// Combined stdout and stderr, cut to the last [`MAX_OUTPUT`] bytes.
+    pub output: String,
+}
+
+/// Runs a task's verify command through the shell in the current directory,
+/// with stdout and stderr captured together.
+pub fn run(command: &str) -> std::io::Result<Outcome> {
+    let (mut reader, writer) = std::io::pipe()?;
+    let mut child = shell(command)
+        .stdin(Stdio::null())
+        .stdout(writer.try_clone()?)
+        .stderr(writer)
+        .spawn()?;
+
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    let status = child.wait()?;
+
+    let mut start = bytes.len().saturating_sub(MAX_OUTPUT);
+    while start < bytes.len() && (bytes[start] & 0xC0) == 0x80 {
+        start += 1;
+    }
+    let mut output = String::from_utf8_lossy(&bytes[start..]).into_owned();
+    if start > 0 {
+        output.insert_str(0, "[output truncated]\n");
+    }
+    Ok(Outcome { status, output })
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
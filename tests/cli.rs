@@ -1,4 +1,52 @@
-use std::process::Command;
+use std::process::{Command, ExitStatus};
+
+use serde_json::{Value, json};
+
+/// What one `pearls --json --db <db> ...` run printed.
+struct Run {
+    status: ExitStatus,
+    stdout: Value,
+    stderr: String,
+}
+
+impl Run {
+    /// The JSON output of a run that must have succeeded.
+    fn json(self) -> Value {
+        assert!(self.status.success(), "pearls failed: {}", self.stderr);
+        self.stdout
+    }
+
+    /// The error message of a run that must have failed without printing
+    /// anything to stdout.
+    fn error(self) -> String {
+        assert!(!self.status.success(), "pearls succeeded: {}", self.stdout);
+        assert_eq!(self.stdout, Value::Null);
+        self.stderr
+    }
+}
+
+/// Runs `pearls --json --db <db> <args>` with the scheduling and retry
+/// environment cleared. Empty stdout parses as `Null`.
+fn pearls(db: &str, args: &[&str]) -> Run {
+    let output = Command::new(assert_cmd::cargo::cargo_bin!("pearls"))
+        .args(["--json", "--db", db])
+        .args(args)
+        .env_remove("PEARLS_SCHEDULE")
+        .env_remove("PEARLS_MAX_ATTEMPTS")
+        .env_remove("PEARLS_RETRY_BACKOFF")
+        .output()
+        .expect("run pearls");
+    let stdout = if output.stdout.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_slice(&output.stdout).expect("json stdout")
+    };
+    Run {
+        status: output.status,
+        stdout,
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+    }
+}
 
 #[test]
 fn json_output_for_add_and_list() {
@@ -48,7 +96,23 @@ fn json_output_for_add_and_list() {
     }
     let list_payload: serde_json::Value =
         serde_json::from_slice(&output.stdout).expect("json list");
-    assert_eq!(list_payload.as_array().map(|arr| arr.len()), Some(1));
+    assert_eq!(list_payload["items"].as_array().map(|arr| arr.len()), Some(1));
+    assert_eq!(list_payload["total"], 1);
+    assert!(list_payload["next_cursor"].is_null());
+
+    let mut bare = Command::new(assert_cmd::cargo::cargo_bin!("pearls"));
+    bare.args([
+        "--json",
+        "--db",
+        db_path.to_str().expect("db path"),
+        "tasks",
+        "list",
+        "--bare-array",
+    ]);
+    let output = bare.output().expect("run bare list");
+    let bare_payload: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("json bare list");
+    assert_eq!(bare_payload.as_array().map(|arr| arr.len()), Some(1));
 }
 
 #[test]
@@ -123,3 +187,1213 @@ fn claim_next_selects_ready_task_and_updates_state() {
     assert_eq!(claim_payload["id"].as_i64(), Some(high_id));
     assert_eq!(claim_payload["state"], "in_progress");
 }
+
+#[test]
+fn export_and_import_round_trip_between_databases() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let source_db = temp.path().join("source.db");
+    let target_db = temp.path().join("target.db");
+    let dump_path = temp.path().join("dump.json");
+
+    let mut add = Command::new(assert_cmd::cargo::cargo_bin!("pearls"));
+    add.args([
+        "--db",
+        source_db.to_str().expect("db path"),
+        "tasks",
+        "add",
+        "--title",
+        "Exported",
+        "--description",
+        "Moves between databases",
+    ]);
+    let output = add.output().expect("run add");
+    if !output.status.success() {
+        panic!(
+            "add failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let mut export = Command::new(assert_cmd::cargo::cargo_bin!("pearls"));
+    export.args([
+        "--db",
+        source_db.to_str().expect("db path"),
+        "export",
+        "--format",
+        "json",
+    ]);
+    let output = export.output().expect("run export");
+    if !output.status.success() {
+        panic!(
+            "export failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    std::fs::write(&dump_path, &output.stdout).expect("write dump");
+
+    let mut import = Command::new(assert_cmd::cargo::cargo_bin!("pearls"));
+    import.args([
+        "--json",
+        "--db",
+        target_db.to_str().expect("db path"),
+        "import",
+        "--from",
+        dump_path.to_str().expect("dump path"),
+    ]);
+    let output = import.output().expect("run import");
+    if !output.status.success() {
+        panic!(
+            "import failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    let import_payload: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("json import");
+    assert_eq!(import_payload["tasks"], 1);
+
+    let mut list = Command::new(assert_cmd::cargo::cargo_bin!("pearls"));
+    list.args([
+        "--json",
+        "--db",
+        target_db.to_str().expect("db path"),
+        "tasks",
+        "list",
+    ]);
+    let output = list.output().expect("run list");
+    let list_payload: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("json list");
+    assert_eq!(list_payload["items"][0]["title"], "Exported");
+}
+
+#[test]
+fn add_with_key_is_idempotent_and_key_resolves_in_show() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let db_path = temp.path().join("pearls.db");
+
+    let mut ids = Vec::new();
+    for _ in 0..2 {
+        let mut add = Command::new(assert_cmd::cargo::cargo_bin!("pearls"));
+        add.args([
+            "--json",
+            "--db",
+            db_path.to_str().expect("db path"),
+            "tasks",
+            "add",
+            "--title",
+            "Build parser",
+            "--description",
+            "Retried tool call",
+            "--key",
+            "build-parser",
+        ]);
+        let output = add.output().expect("run add");
+        if !output.status.success() {
+            panic!(
+                "add failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        let add_payload: serde_json::Value =
+            serde_json::from_slice(&output.stdout).expect("json add");
+        assert_eq!(add_payload["key"], "build-parser");
+        ids.push(add_payload["id"].as_i64().expect("id"));
+    }
+    assert_eq!(ids[0], ids[1]);
+
+    let mut show = Command::new(assert_cmd::cargo::cargo_bin!("pearls"));
+    show.args([
+        "--json",
+        "--db",
+        db_path.to_str().expect("db path"),
+        "tasks",
+        "show",
+        "--id",
+        "build-parser",
+    ]);
+    let output = show.output().expect("run show");
+    let show_payload: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("json show");
+    assert_eq!(show_payload["id"].as_i64(), Some(ids[0]));
+
+    let mut show = Command::new(assert_cmd::cargo::cargo_bin!("pearls"));
+    show.args([
+        "--db",
+        db_path.to_str().expect("db path"),
+        "tasks",
+        "show",
+        "--id",
+        "build-parser",
+        "--format",
+        "{id}\\t{key}",
+    ]);
+    let output = show.output().expect("run show with format");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!("{}\tbuild-parser\n", ids[0])
+    );
+
+    let mut show = Command::new(assert_cmd::cargo::cargo_bin!("pearls"));
+    show.args([
+        "--json",
+        "--db",
+        db_path.to_str().expect("db path"),
+        "tasks",
+        "show",
+        "--id",
+        "build-parser",
+        "--fields",
+        "id,title",
+    ]);
+    let output = show.output().expect("run show with fields");
+    let show_payload: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("json show fields");
+    assert_eq!(
+        show_payload,
+        serde_json::json!({ "schema_version": 1, "id": ids[0], "title": "Build parser" })
+    );
+}
+
+/// `schemas/` holds the published schema for every command as a snapshot of
+/// `pearls schema`. Set `PEARLS_UPDATE_SCHEMAS=1` to rewrite the files after
+/// an intentional output change (and bump `SCHEMA_VERSION` if it breaks
+/// consumers).
+#[test]
+fn published_schemas_match_schema_command() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let mut schema = Command::new(assert_cmd::cargo::cargo_bin!("pearls"));
+    schema.current_dir(temp.path()).arg("schema");
+    let output = schema.output().expect("run schema");
+    assert!(output.status.success());
+    let schemas: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("json schemas");
+    let schemas = schemas.as_object().expect("schema map");
+    assert!(
+        !temp.path().join("pearls.db").exists(),
+        "schema should not open the database"
+    );
+
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("schemas");
+    let update = std::env::var_os("PEARLS_UPDATE_SCHEMAS").is_some();
+    if update {
+        std::fs::create_dir_all(&dir).expect("create schemas dir");
+    }
+    for (name, schema) in schemas {
+        let path = dir.join(format!("{name}.json"));
+        let rendered = format!(
+            "{}\n",
+            serde_json::to_string_pretty(schema).expect("render schema")
+        );
+        if update {
+            std::fs::write(&path, &rendered).expect("write schema");
+            continue;
+        }
+        let published = std::fs::read_to_string(&path)
+            .unwrap_or_else(|err| panic!("read {}: {err}", path.display()));
+        assert_eq!(
+            published, rendered,
+            "{name} schema changed; rerun with PEARLS_UPDATE_SCHEMAS=1"
+        );
+    }
+
+    let mut published: Vec<String> = std::fs::read_dir(&dir)
+        .expect("read schemas dir")
+        .map(|entry| {
+            let path = entry.expect("dir entry").path();
+            path.file_stem()
+                .expect("file stem")
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect();
+    published.sort();
+    let mut expected: Vec<String> = schemas.keys().cloned().collect();
+    expected.sort();
+    assert_eq!(published, expected, "stale files in schemas/");
+}
+
+#[test]
+fn completions_offer_open_task_ids_from_the_database() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let db_path = temp.path().join("pearls.db");
+
+    for (title, state) in [("Parser work", None), ("Old docs", Some("closed"))] {
+        let mut add = Command::new(assert_cmd::cargo::cargo_bin!("pearls"));
+        add.args([
+            "--json",
+            "--db",
+            db_path.to_str().expect("db path"),
+            "tasks",
+            "add",
+            "--title",
+            title,
+            "--description",
+            "d",
+        ]);
+        let output = add.output().expect("run add");
+        let payload: serde_json::Value =
+            serde_json::from_slice(&output.stdout).expect("json add");
+        if let Some(state) = state {
+            let id = payload["id"].as_i64().expect("id").to_string();
+            let mut update = Command::new(assert_cmd::cargo::cargo_bin!("pearls"));
+            update.args([
+                "--db",
+                db_path.to_str().expect("db path"),
+                "tasks",
+                "update-metadata",
+                "--id",
+                &id,
+                "--state",
+                state,
+            ]);
+            assert!(update.output().expect("run update").status.success());
+        }
+    }
+
+    let mut script = Command::new(assert_cmd::cargo::cargo_bin!("pearls"));
+    script.args(["completions", "zsh"]);
+    let output = script.output().expect("run completions");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("compdef"));
+
+    let mut complete = Command::new(assert_cmd::cargo::cargo_bin!("pearls"));
+    complete
+        .env("COMPLETE", "fish")
+        .env("PEARLS_DB", &db_path)
+        .args(["--", "pearls", "tasks", "show", "--id", ""]);
+    let output = complete.output().expect("run completion");
+    let candidates = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(candidates, "1\t[ready] Parser work\n");
+
+    // Completing against a database that hasn't been set up must not touch it.
+    let blank = temp.path().join("blank.db");
+    std::fs::write(&blank, "").expect("write blank db");
+    let output = Command::new(assert_cmd::cargo::cargo_bin!("pearls"))
+        .env("COMPLETE", "fish")
+        .env("PEARLS_DB", &blank)
+        .args(["--", "pearls", "tasks", "show", "--id", ""])
+        .output()
+        .expect("run completion");
+    assert!(output.stdout.is_empty());
+    assert_eq!(std::fs::metadata(&blank).expect("blank db").len(), 0);
+}
+
+#[test]
+fn claim_next_wait_picks_up_task_unblocked_by_another_process() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let db_path = temp.path().join("pearls.db");
+    let db = db_path.to_str().expect("db path");
+
+    let add = |args: &[&str]| {
+        let mut add = Command::new(assert_cmd::cargo::cargo_bin!("pearls"));
+        add.args(["--json", "--db", db, "tasks", "add"]).args(args);
+        let output = add.output().expect("run add");
+        assert!(output.status.success());
+        let payload: serde_json::Value =
+            serde_json::from_slice(&output.stdout).expect("json add");
+        payload["id"].as_i64().expect("id").to_string()
+    };
+    let parent = add(&["--title", "Parent", "--description", "p"]);
+    let child = add(&["--title", "Child", "--description", "c", "--child-of", &parent]);
+    let mut claim_parent = Command::new(assert_cmd::cargo::cargo_bin!("pearls"));
+    claim_parent.args(["--db", db, "tasks", "claim-next"]);
+    assert!(claim_parent.output().expect("claim parent").status.success());
+
+    let mut timed_out = Command::new(assert_cmd::cargo::cargo_bin!("pearls"));
+    timed_out.args([
+        "--json",
+        "--db",
+        db,
+        "tasks",
+        "claim-next",
+        "--wait",
+        "--timeout",
+        "300ms",
+    ]);
+    let output = timed_out.output().expect("run timed out claim");
+    let payload: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("json timed out claim");
+    assert_eq!(payload["status"], "no_ready_tasks");
+
+    let waiting = std::process::Command::new(assert_cmd::cargo::cargo_bin!("pearls"))
+        .args([
+            "--json",
+            "--db",
+            db,
+            "tasks",
+            "claim-next",
+            "--wait",
+            "--timeout",
+            "20s",
+        ])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("spawn waiting claim");
+    std::thread::sleep(std::time::Duration::from_millis(500));
+
+    let mut close = Command::new(assert_cmd::cargo::cargo_bin!("pearls"));
+    close.args([
+        "--db",
+        db,
+        "tasks",
+        "update-metadata",
+        "--id",
+        &parent,
+        "--state",
+        "closed",
+    ]);
+    assert!(close.output().expect("close parent").status.success());
+
+    let output = waiting.wait_with_output().expect("waiting claim");
+    let payload: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("json waiting claim");
+    assert_eq!(payload["id"].as_i64().map(|id| id.to_string()), Some(child));
+    assert_eq!(payload["state"], "in_progress");
+}
+
+#[cfg(unix)]
+#[test]
+fn hooks_run_for_claim_close_and_unblock() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp = tempfile::tempdir().expect("tempdir");
+    let db_path = temp.path().join("pearls.db");
+    let db = db_path.to_str().expect("db path");
+    let log = temp.path().join("hooks.log");
+    let hooks_dir = temp.path().join(".pearls").join("hooks");
+    std::fs::create_dir_all(&hooks_dir).expect("hooks dir");
+    for name in ["on-claim", "on-close", "on-unblock"] {
+        let script = hooks_dir.join(name);
+        std::fs::write(
+            &script,
+            format!(
+                "#!/bin/sh\nread task\necho \"$PEARLS_HOOK $PEARLS_TASK_ID $task\" >> '{}'\n",
+                log.display()
+            ),
+        )
+        .expect("write hook");
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))
+            .expect("chmod hook");
+    }
+
+    let run = |args: &[&str]| {
+        let run = pearls(db, args);
+        assert!(run.stderr.is_empty(), "pearls {args:?}: {}", run.stderr);
+        run.json()
+    };
+    run(&["tasks", "add", "--title", "Parent", "--description", "p"]);
+    run(&[
+        "tasks",
+        "add",
+        "--title",
+        "Child",
+        "--description",
+        "c",
+        "--child-of",
+        "1",
+    ]);
+    assert_eq!(run(&["tasks", "claim-next"])["id"], 1);
+    run(&["tasks", "update-metadata", "--id", "1", "--state", "closed"]);
+
+    let log = std::fs::read_to_string(&log).expect("hook log");
+    let fired: Vec<(&str, &str)> = log
+        .lines()
+        .map(|line| {
+            let mut parts = line.splitn(3, ' ');
+            (parts.next().unwrap_or(""), parts.next().unwrap_or(""))
+        })
+        .collect();
+    assert_eq!(
+        fired,
+        vec![("on-claim", "1"), ("on-close", "1"), ("on-unblock", "2")]
+    );
+    let task: Value = serde_json::from_str(
+        log.lines()
+            .last()
+            .and_then(|line| line.splitn(3, ' ').nth(2))
+            .expect("task json"),
+    )
+    .expect("hook stdin json");
+    assert_eq!(task["title"], "Child");
+    assert_eq!(task["state"], "ready");
+}
+
+#[cfg(unix)]
+#[test]
+fn close_runs_verify_and_records_failures_as_notes() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let db_path = temp.path().join("pearls.db");
+    let db = db_path.to_str().expect("db path");
+    let marker = temp.path().join("done");
+    let verify = format!("echo checking; test -e '{}'", marker.display());
+
+    let task = pearls(
+        db,
+        &[
+            "tasks",
+            "add",
+            "--title",
+            "Parser",
+            "--description",
+            "d",
+            "--verify",
+            &verify,
+        ],
+    )
+    .json();
+    assert_eq!(task["verify"], verify.as_str());
+
+    let refused = pearls(db, &["tasks", "close", "--id", "1"]);
+    assert!(!refused.status.success());
+    assert_eq!(refused.stdout["status"], "verify_failed");
+    assert_eq!(refused.stdout["exit_code"], 1);
+    assert!(
+        refused.stdout["note"]["body"]
+            .as_str()
+            .is_some_and(|body| body.contains("checking"))
+    );
+    let notes = pearls(db, &["tasks", "notes", "--id", "1"]).json();
+    assert_eq!(notes.as_array().map(Vec::len), Some(1));
+    assert_eq!(
+        pearls(
+            db,
+            &["tasks", "update-metadata", "--id", "1", "--state", "closed"]
+        )
+        .error(),
+        "task #1 has a verify command; close it with `pearls tasks close --id 1`\n"
+    );
+    assert_eq!(
+        pearls(db, &["tasks", "show", "--id", "1"]).json()["state"],
+        "ready"
+    );
+
+    std::fs::write(&marker, "").expect("write marker");
+    assert_eq!(
+        pearls(db, &["tasks", "close", "--id", "1"]).json()["state"],
+        "closed"
+    );
+
+    pearls(
+        db,
+        &[
+            "tasks",
+            "add",
+            "--title",
+            "Docs",
+            "--description",
+            "d",
+            "--verify",
+            "false",
+        ],
+    )
+    .json();
+    assert_eq!(
+        pearls(db, &["tasks", "close", "--id", "2", "--skip-verify"]).json()["state"],
+        "closed"
+    );
+}
+
+#[test]
+fn close_requires_checked_criteria_unless_forced() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let db_path = temp.path().join("pearls.db");
+    let db = db_path.to_str().expect("db path");
+
+    pearls(
+        db,
+        &["tasks", "add", "--title", "Parser", "--description", "d"],
+    )
+    .json();
+    let task = pearls(
+        db,
+        &[
+            "tasks",
+            "criteria",
+            "add",
+            "--id",
+            "1",
+            "--text",
+            "handles empty input",
+            "--text",
+            "has tests",
+        ],
+    )
+    .json();
+    assert_eq!(
+        task["criteria"],
+        json!([
+            { "text": "handles empty input", "checked": false },
+            { "text": "has tests", "checked": false }
+        ])
+    );
+
+    let task = pearls(
+        db,
+        &["tasks", "criteria", "check", "--id", "1", "--item", "2"],
+    )
+    .json();
+    assert_eq!(task["criteria"][1]["checked"], true);
+    let error = pearls(db, &["tasks", "close", "--id", "1"]).error();
+    assert!(
+        error.starts_with("refusing to close #1: 1 acceptance criteria unchecked\n"),
+        "{error}"
+    );
+    assert!(
+        error.ends_with("check them with `pearls tasks criteria check`, or pass --force\n"),
+        "{error}"
+    );
+    assert_eq!(
+        pearls(
+            db,
+            &["tasks", "criteria", "check", "--id", "1", "--item", "3"]
+        )
+        .error(),
+        "failed to update criteria: Custom Error: task 1 has no criterion 3 (it has 2)\n"
+    );
+
+    pearls(db, &["tasks", "criteria", "check", "--id", "1", "--all"]).json();
+    assert_eq!(
+        pearls(db, &["tasks", "close", "--id", "1"]).json()["state"],
+        "closed"
+    );
+
+    pearls(
+        db,
+        &["tasks", "add", "--title", "Docs", "--description", "d"],
+    )
+    .json();
+    pearls(
+        db,
+        &[
+            "tasks", "criteria", "add", "--id", "2", "--text", "reviewed",
+        ],
+    )
+    .json();
+    assert_eq!(
+        pearls(db, &["tasks", "claim-next"]).json()["criteria"][0]["text"],
+        "reviewed"
+    );
+    assert_eq!(
+        pearls(db, &["tasks", "close", "--id", "2", "--force"]).json()["state"],
+        "closed"
+    );
+}
+
+#[test]
+fn fail_requeues_with_backoff_then_parks_for_a_human() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let db_path = temp.path().join("pearls.db");
+    let db = db_path.to_str().expect("db path");
+
+    pearls(
+        db,
+        &["tasks", "add", "--title", "Flaky", "--description", "d"],
+    )
+    .json();
+
+    assert_eq!(
+        pearls(
+            db,
+            &["tasks", "fail", "--id", "1", "--reason", "not claimed"]
+        )
+        .error(),
+        "failed to record failure: Custom Error: task 1 is ready, not in_progress\n"
+    );
+    assert_eq!(pearls(db, &["tasks", "claim-next"]).json()["id"], 1);
+    let task = pearls(
+        db,
+        &[
+            "tasks",
+            "fail",
+            "--id",
+            "1",
+            "--reason",
+            "tests timed out",
+            "--max-attempts",
+            "2",
+            "--backoff",
+            "1h",
+        ],
+    )
+    .json();
+    assert_eq!(task["state"], "deferred");
+    assert_eq!(task["attempts"], 1);
+    assert!(task["not_before"].is_i64());
+    assert_eq!(
+        pearls(db, &["tasks", "claim-next"]).json()["status"],
+        "no_ready_tasks"
+    );
+
+    pearls(
+        db,
+        &[
+            "tasks",
+            "update-metadata",
+            "--id",
+            "1",
+            "--state",
+            "in_progress",
+        ],
+    )
+    .json();
+    let task = pearls(
+        db,
+        &[
+            "tasks",
+            "fail",
+            "--id",
+            "1",
+            "--reason",
+            "tests timed out again",
+            "--max-attempts",
+            "2",
+        ],
+    )
+    .json();
+    assert_eq!(task["state"], "needs_human");
+    assert_eq!(task["attempts"], 2);
+
+    let notes = pearls(db, &["tasks", "notes", "--id", "1"]).json();
+    assert_eq!(notes[0]["body"], "attempt 1 of 2 failed: tests timed out");
+    assert_eq!(
+        notes[1]["body"],
+        "attempt 2 of 2 failed: tests timed out again"
+    );
+
+    let task = pearls(
+        db,
+        &["tasks", "update-metadata", "--id", "1", "--state", "ready"],
+    )
+    .json();
+    assert_eq!(task["state"], "ready");
+    assert_eq!(task["attempts"], 0);
+}
+
+#[test]
+fn snoozed_and_future_tasks_are_deferred_until_their_start_time() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let db_path = temp.path().join("pearls.db");
+    let db = db_path.to_str().expect("db path");
+
+    let task = pearls(
+        db,
+        &[
+            "tasks",
+            "add",
+            "--title",
+            "Release",
+            "--description",
+            "d",
+            "--not-before",
+            "2100-01-01T09:30Z",
+        ],
+    )
+    .json();
+    assert_eq!(task["state"], "deferred");
+    assert_eq!(task["not_before"], 4_102_479_000_i64);
+
+    pearls(
+        db,
+        &["tasks", "add", "--title", "Docs", "--description", "d"],
+    )
+    .json();
+    let task = pearls(db, &["tasks", "snooze", "--id", "2", "--for", "2h"]).json();
+    assert_eq!(task["state"], "deferred");
+    assert_eq!(
+        pearls(db, &["tasks", "claim-next"]).json()["status"],
+        "no_ready_tasks"
+    );
+    assert_eq!(
+        pearls(
+            db,
+            &["tasks", "list", "--state", "deferred", "--bare-array"]
+        )
+        .json()
+        .as_array()
+        .map(Vec::len),
+        Some(2)
+    );
+
+    let task = pearls(
+        db,
+        &["tasks", "update-metadata", "--id", "1", "--not-before", ""],
+    )
+    .json();
+    assert_eq!(task["state"], "ready");
+    assert_eq!(task["not_before"], Value::Null);
+    assert_eq!(pearls(db, &["tasks", "claim-next"]).json()["id"], 1);
+}
+
+#[test]
+fn due_dates_drive_overdue_listing_and_deadline_claims() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let db_path = temp.path().join("pearls.db");
+    let db = db_path.to_str().expect("db path");
+
+    pearls(
+        db,
+        &["tasks", "add", "--title", "Important", "--description", "d"],
+    )
+    .json();
+    let task = pearls(
+        db,
+        &[
+            "tasks",
+            "add",
+            "--title",
+            "Report",
+            "--description",
+            "d",
+            "--priority",
+            "4",
+            "--due",
+            "2020-01-01",
+        ],
+    )
+    .json();
+    assert_eq!(task["due_at"], 1_577_836_800_i64);
+
+    for date in ["2026-02-31", "2023-02-29", "2026-04-31"] {
+        let run = pearls(
+            db,
+            &["tasks", "update-metadata", "--id", "2", "--due", date],
+        );
+        assert_eq!(run.status.code(), Some(2));
+        assert!(
+            run.stderr.contains(&format!("invalid time \"{date}\"")),
+            "{}",
+            run.stderr
+        );
+    }
+    let task = pearls(
+        db,
+        &[
+            "tasks",
+            "update-metadata",
+            "--id",
+            "1",
+            "--due",
+            "2024-02-29",
+        ],
+    )
+    .json();
+    assert_eq!(task["due_at"], 1_709_164_800_i64);
+    pearls(db, &["tasks", "update-metadata", "--id", "1", "--due", ""]).json();
+
+    let overdue = pearls(db, &["tasks", "list", "--overdue", "--bare-array"]).json();
+    let ids: Vec<&Value> = overdue
+        .as_array()
+        .map(|tasks| tasks.iter().map(|task| &task["id"]).collect())
+        .unwrap_or_default();
+    assert_eq!(ids, [&json!(2)]);
+
+    assert_eq!(
+        pearls(db, &["tasks", "claim-next", "--schedule", "deadline"]).json()["id"],
+        2
+    );
+    assert_eq!(pearls(db, &["tasks", "claim-next"]).json()["id"], 1);
+}
+
+#[test]
+fn show_reports_priority_inherited_from_blocked_work() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let db_path = temp.path().join("pearls.db");
+    let db = db_path.to_str().expect("db path");
+
+    pearls(
+        db,
+        &[
+            "tasks",
+            "add",
+            "--title",
+            "Schema",
+            "--description",
+            "d",
+            "--priority",
+            "5",
+        ],
+    )
+    .json();
+    pearls(
+        db,
+        &[
+            "tasks",
+            "add",
+            "--title",
+            "Cleanup",
+            "--description",
+            "d",
+            "--priority",
+            "2",
+        ],
+    )
+    .json();
+    pearls(
+        db,
+        &[
+            "tasks",
+            "add",
+            "--title",
+            "Launch",
+            "--description",
+            "d",
+            "--priority",
+            "1",
+            "--child-of",
+            "1",
+        ],
+    )
+    .json();
+
+    let task = pearls(db, &["tasks", "show", "--id", "1"]).json();
+    assert_eq!(task["priority"], 5);
+    assert_eq!(task["effective_priority"], 1);
+    assert_eq!(
+        pearls(db, &["tasks", "claim-next", "--schedule", "inherited"]).json()["id"],
+        1
+    );
+    assert_eq!(pearls(db, &["tasks", "claim-next"]).json()["id"], 2);
+}
+
+#[test]
+fn csv_update_refuses_to_close_tasks_with_checks() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let db_path = temp.path().join("pearls.db");
+    let db = db_path.to_str().expect("db path");
+    let csv_path = temp.path().join("tasks.csv");
+    let csv = csv_path.to_str().expect("csv path");
+
+    pearls(
+        db,
+        &["tasks", "add", "--title", "Plain", "--description", "d"],
+    )
+    .json();
+    pearls(
+        db,
+        &[
+            "tasks",
+            "add",
+            "--title",
+            "Checked",
+            "--description",
+            "d",
+            "--verify",
+            "false",
+        ],
+    )
+    .json();
+    pearls(
+        db,
+        &["tasks", "add", "--title", "Listed", "--description", "d"],
+    )
+    .json();
+    pearls(
+        db,
+        &["tasks", "criteria", "add", "--id", "3", "--text", "works"],
+    )
+    .json();
+
+    let import = |contents: &str| {
+        std::fs::write(&csv_path, contents).expect("write csv");
+        pearls(
+            db,
+            &["import", "--format", "csv", "--update", "--from", csv],
+        )
+    };
+    for (id, reason) in [
+        ("2", "has a verify command"),
+        ("3", "has unchecked acceptance criteria"),
+    ] {
+        assert_eq!(
+            import(&format!("id,state\n1,closed\n{id},closed\n")).error(),
+            format!(
+                "line 3: task #{id} {reason}; close it with `pearls tasks close --id {id}`\n\
+                 no tasks were updated\n"
+            )
+        );
+        for id in ["1", id] {
+            assert_eq!(
+                pearls(db, &["tasks", "show", "--id", id]).json()["state"],
+                "ready"
+            );
+        }
+    }
+
+    assert_eq!(import("id,state\n1,closed\n").json()["updated"], 1);
+    assert_eq!(
+        pearls(db, &["tasks", "show", "--id", "1"]).json()["state"],
+        "closed"
+    );
+}
+
+#[test]
+fn derived_states_cannot_be_written() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let db_path = temp.path().join("pearls.db");
+    let db = db_path.to_str().expect("db path");
+    let csv_path = temp.path().join("tasks.csv");
+    let csv = csv_path.to_str().expect("csv path");
+    let dump_path = temp.path().join("dump.json");
+    let dump = dump_path.to_str().expect("dump path");
+    let sync_path = temp.path().join("tasks.jsonl");
+    let sync = sync_path.to_str().expect("sync path");
+
+    pearls(
+        db,
+        &["tasks", "add", "--title", "Plain", "--description", "d"],
+    )
+    .json();
+    pearls(db, &["sync", "--export", sync]).json();
+    let synced = std::fs::read_to_string(&sync_path).expect("read sync file");
+
+    for state in ["deferred", "blocked"] {
+        assert_eq!(
+            pearls(
+                db,
+                &["tasks", "update-metadata", "--id", "1", "--state", state]
+            )
+            .error(),
+            format!(
+                "failed to update task: Custom Error: `{state}` is derived from parents and \
+                 not-before times and can't be set\n"
+            )
+        );
+
+        std::fs::write(&csv_path, format!("id,state\n1,{state}\n")).expect("write csv");
+        assert_eq!(
+            pearls(
+                db,
+                &["import", "--format", "csv", "--update", "--from", csv]
+            )
+            .error(),
+            format!(
+                "line 2: `{state}` is derived from parents and not-before times and can't be \
+                 set\nno tasks were updated\n"
+            )
+        );
+
+        let contents = json!({
+            "version": 1,
+            "tasks": [{ "id": 2, "title": "Later", "priority": 1, "state": state }],
+            "dependencies": [],
+        });
+        std::fs::write(&dump_path, contents.to_string()).expect("write dump");
+        assert_eq!(
+            pearls(db, &["import", "--from", dump]).error(),
+            format!(
+                "failed to import tasks: Custom Error: task 2 has state \"{state}\", which is \
+                 derived from parents and not-before times and can't be imported\n"
+            )
+        );
+
+        let edited = synced.replace("\"state\":\"ready\"", &format!("\"state\":\"{state}\""));
+        assert_ne!(edited, synced);
+        std::fs::write(&sync_path, edited).expect("write sync file");
+        assert_eq!(
+            pearls(db, &["sync", "--import", sync]).error(),
+            format!(
+                "failed to merge sync file: Custom Error: task 1 has state \"{state}\", which \
+                 is derived from parents and not-before times and can't be synced\n"
+            )
+        );
+
+        assert_eq!(
+            pearls(db, &["tasks", "show", "--id", "1"]).json()["state"],
+            "ready"
+        );
+    }
+}
+
+#[test]
+fn delays_too_long_to_store_are_rejected() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let db_path = temp.path().join("pearls.db");
+    let db = db_path.to_str().expect("db path");
+
+    pearls(
+        db,
+        &["tasks", "add", "--title", "Docs", "--description", "d"],
+    )
+    .json();
+    for args in [
+        &["tasks", "snooze", "--id", "1", "--for", "999999999999999d"][..],
+        &[
+            "tasks",
+            "add",
+            "--title",
+            "Later",
+            "--description",
+            "d",
+            "--not-before",
+            "999999999999999d",
+        ],
+        &[
+            "tasks",
+            "update-metadata",
+            "--id",
+            "1",
+            "--not-before",
+            "999999999999999d",
+        ],
+    ] {
+        let error = pearls(db, args).error();
+        assert!(
+            error.contains("delay \"999999999999999d\" is too long"),
+            "{error}"
+        );
+    }
+    let tasks = pearls(db, &["tasks", "list", "--bare-array"]).json();
+    assert_eq!(tasks.as_array().map(Vec::len), Some(1));
+    assert_eq!(tasks[0]["not_before"], Value::Null);
+}
+
+#[test]
+fn claim_next_wait_with_an_unrepresentable_timeout_keeps_waiting() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let db_path = temp.path().join("pearls.db");
+    let db = db_path.to_str().expect("db path");
+
+    let mut waiting = Command::new(assert_cmd::cargo::cargo_bin!("pearls"))
+        .args([
+            "--json",
+            "--db",
+            db,
+            "tasks",
+            "claim-next",
+            "--wait",
+            "--timeout",
+            "999999999999999d",
+        ])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("spawn waiting claim");
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    let exited = waiting.try_wait().expect("poll waiting claim");
+    waiting.kill().expect("stop waiting claim");
+    waiting.wait().expect("reap waiting claim");
+    assert!(exited.is_none(), "claim-next exited early: {exited:?}");
+}
+
+#[test]
+fn fail_with_a_huge_backoff_defers_for_as_long_as_possible() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let db_path = temp.path().join("pearls.db");
+    let db = db_path.to_str().expect("db path");
+
+    pearls(
+        db,
+        &["tasks", "add", "--title", "Flaky", "--description", "d"],
+    )
+    .json();
+    assert_eq!(pearls(db, &["tasks", "claim-next"]).json()["id"], 1);
+    let task = pearls(
+        db,
+        &[
+            "tasks",
+            "fail",
+            "--id",
+            "1",
+            "--reason",
+            "broken",
+            "--backoff",
+            "999999999999999d",
+        ],
+    )
+    .json();
+    assert_eq!(task["state"], "deferred");
+    assert_eq!(task["not_before"], i64::MAX);
+    assert_eq!(
+        pearls(db, &["tasks", "claim-next"]).json()["status"],
+        "no_ready_tasks"
+    );
+}
+
+#[test]
+fn task_ids_outside_the_valid_range_are_rejected() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let db_path = temp.path().join("pearls.db");
+    let db = db_path.to_str().expect("db path");
+
+    for (id, message) in [
+        (
+            "18446744073709551615",
+            "task id 18446744073709551615 is too large",
+        ),
+        ("0", "task id 0 must be positive"),
+        ("-3", "task id -3 must be positive"),
+    ] {
+        let run = pearls(db, &["tasks", "show", &format!("--id={id}")]);
+        assert_eq!(run.status.code(), Some(2));
+        assert!(run.stderr.contains(message), "{}", run.stderr);
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn close_checks_the_task_again_after_verify_runs() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let db_path = temp.path().join("pearls.db");
+    let db = db_path.to_str().expect("db path");
+    let bin = assert_cmd::cargo::cargo_bin!("pearls");
+
+    for (id, change) in [
+        ("1", "tasks criteria add --id 1 --text 'late check'"),
+        ("2", "tasks close --id 2 --skip-verify"),
+    ] {
+        let verify = format!("'{}' --db '{db}' {change} >/dev/null", bin.display());
+        pearls(
+            db,
+            &[
+                "tasks",
+                "add",
+                "--title",
+                "Racy",
+                "--description",
+                "d",
+                "--verify",
+                &verify,
+            ],
+        )
+        .json();
+        let error = pearls(db, &["tasks", "close", "--id", id]).error();
+        let expected = match id {
+            "1" => "refusing to close #1: 1 acceptance criteria unchecked\n",
+            _ => "task #2 is already closed\n",
+        };
+        assert!(error.starts_with(expected), "{error}");
+    }
+    assert_eq!(
+        pearls(db, &["tasks", "show", "--id", "1"]).json()["state"],
+        "ready"
+    );
+}
+
+#[test]
+fn list_limit_defaults_to_twenty_except_when_streaming() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let db_path = temp.path().join("pearls.db");
+    let db = db_path.to_str().expect("db path");
+
+    for n in 1..=25 {
+        let title = format!("Task {n}");
+        pearls(db, &["tasks", "add", "--title", &title, "--description", "d"]).json();
+    }
+    let count = |args: &[&str]| {
+        let items = pearls(db, args).json()["items"].clone();
+        items.as_array().expect("items").len()
+    };
+    assert_eq!(count(&["tasks", "list"]), 20);
+    assert_eq!(count(&["tasks", "list", "--limit", "5"]), 5);
+
+    let stream = |args: &[&str]| {
+        let output = Command::new(assert_cmd::cargo::cargo_bin!("pearls"))
+            .args(["--db", db, "tasks", "list", "--json-lines"])
+            .args(args)
+            .output()
+            .expect("run list");
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).lines().count()
+    };
+    assert_eq!(stream(&[]), 25);
+    assert_eq!(stream(&["--limit", "3"]), 3);
+}